@@ -0,0 +1,147 @@
+use crate::shared::Point;
+use termion::terminal_size;
+
+///
+/// The part of a map that is on screen: the first point drawn, how many
+/// points fit each way, how far it is pushed in to center a map that is
+/// smaller than the screen, and how wide the screen is.
+///
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Camera {
+    pub origin: Point,
+    pub size: (usize, usize),
+    pub offset: (usize, usize),
+    pub width: usize,
+}
+
+///
+/// The least of a map that has to be visible, maps larger than this scroll
+/// once the screen fits it.
+///
+pub const MIN_VIEW: (usize, usize) = (20, 10);
+
+fn axis(len: usize, focus: usize, screen: usize) -> (usize, usize, usize) {
+    if len <= screen {
+        return (0, len, (screen - len) / 2);
+    }
+
+    let origin = focus.saturating_sub(screen / 2).min(len - screen);
+    (origin, screen, 0)
+}
+
+impl Camera {
+    ///
+    /// Keeps `focus` in the middle of `screen`, as far as the edges of a map
+    /// of `size` allow.
+    ///
+    pub fn follow(size: (usize, usize), focus: &Point, screen: (usize, usize)) -> Camera {
+        let (x, width, left) = axis(size.0, focus.x, screen.0.max(1));
+        let (y, height, top) = axis(size.1, focus.y, screen.1.max(1));
+
+        Camera {
+            origin: Point { x, y },
+            size: (width, height),
+            offset: (left, top),
+            width: screen.0,
+        }
+    }
+
+    ///
+    /// Where `point` ends up on screen, one-based as `cursor::Goto` wants it.
+    ///
+    pub fn on_screen(&self, point: &Point) -> Option<(u16, u16)> {
+        let (x, y) = (
+            point.x.checked_sub(self.origin.x)?,
+            point.y.checked_sub(self.origin.y)?,
+        );
+        if x >= self.size.0 || y >= self.size.1 {
+            return None;
+        }
+
+        Some((
+            (x + self.offset.0 + 1) as u16,
+            (y + self.offset.1 + 1) as u16,
+        ))
+    }
+}
+
+///
+/// The least screen showing all of a map up to `MIN_VIEW`, with `below` under
+/// it, needs.
+///
+pub fn needed(map: (usize, usize), below: (usize, usize)) -> (usize, usize) {
+    (
+        map.0.min(MIN_VIEW.0).max(below.0),
+        map.1.min(MIN_VIEW.1) + below.1,
+    )
+}
+
+///
+/// The notice shown instead of a screen that doesn't fit.
+///
+pub fn too_small(screen: (usize, usize), need: (usize, usize)) -> Option<Vec<String>> {
+    if screen.0 >= need.0 && screen.1 >= need.1 {
+        return None;
+    }
+
+    Some(vec![
+        format!("terminal too small, need {}x{}", need.0, need.1),
+        format!("currently {}x{}", screen.0, screen.1),
+    ])
+}
+
+pub fn screen_size() -> (usize, usize) {
+    match terminal_size() {
+        Ok((width, height)) => (width as usize, height as usize),
+        Err(_) => (80, 24),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_center_maps_smaller_than_the_screen() {
+        let camera = Camera::follow((25, 9), &Point { x: 2, y: 7 }, (80, 20));
+
+        assert_eq!(camera.origin, Point { x: 0, y: 0 });
+        assert_eq!(camera.size, (25, 9));
+        assert_eq!(camera.offset, (27, 5));
+        assert_eq!(camera.on_screen(&Point { x: 2, y: 7 }), Some((30, 13)));
+    }
+
+    #[test]
+    fn it_should_follow_the_focus_and_stop_at_the_edges() {
+        let follow = |x| {
+            Camera::follow((100, 9), &Point { x, y: 0 }, (20, 9))
+                .origin
+                .x
+        };
+
+        assert_eq!(follow(3), 0);
+        assert_eq!(follow(50), 40);
+        assert_eq!(follow(97), 80);
+    }
+
+    #[test]
+    fn it_should_leave_points_outside_the_view_off_screen() {
+        let camera = Camera::follow((100, 100), &Point { x: 50, y: 50 }, (10, 10));
+
+        assert_eq!(camera.on_screen(&Point { x: 45, y: 45 }), Some((1, 1)));
+        assert_eq!(camera.on_screen(&Point { x: 44, y: 50 }), None);
+        assert_eq!(camera.on_screen(&Point { x: 55, y: 50 }), None);
+    }
+
+    #[test]
+    fn it_should_ask_for_room_for_small_maps_and_the_lines_below() {
+        assert_eq!(needed((25, 9), (30, 4)), (30, 13));
+        assert_eq!(needed((100, 50), (0, 0)), MIN_VIEW);
+
+        assert_eq!(too_small((30, 13), (30, 13)), None);
+        assert_eq!(
+            too_small((80, 12), (30, 13)).unwrap()[0],
+            "terminal too small, need 30x13"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    files::Source,
+    helper::parse_all,
+    map::Map,
+    pathfinding::adjacent,
+    shared::{ParsedMap, Tile},
+};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+fn warning(line: usize, message: String) -> Diagnostic {
+    Diagnostic {
+        line,
+        column: 1,
+        severity: Severity::Warning,
+        message,
+    }
+}
+
+///
+/// Floor that can't be walked to from the player start or from any warp,
+/// counted per connected part of the map.
+///
+fn unreachable(line: usize, map: &ParsedMap, out: &mut Vec<Diagnostic>) {
+    let parsed = Map::parse_map(&map.grid, &map.max, &map.player, &map.props);
+    let navigation = &parsed.navigation;
+
+    let mut queue = parsed
+        .grid
+        .iter()
+        .filter(|(_, tile)| **tile == Tile::Warp)
+        .map(|(point, _)| point.to_owned())
+        .collect::<Vec<_>>();
+    queue.push(parsed.current);
+
+    let mut reached = queue.iter().cloned().collect::<HashSet<_>>();
+    while let Some(point) = queue.pop() {
+        for next in adjacent(navigation, &point) {
+            if reached.insert(next) {
+                queue.push(next);
+            }
+        }
+    }
+
+    let mut regions = BTreeMap::new();
+    for (point, tile) in &parsed.grid {
+        if !tile.is_floor() || reached.contains(point) {
+            continue;
+        }
+
+        let region = regions
+            .entry(navigation.component(point))
+            .or_insert((point.to_owned(), 0));
+        region.0 = point.min(&region.0).to_owned();
+        region.1 += 1;
+    }
+
+    let mut regions = regions.values().collect::<Vec<_>>();
+    regions.sort();
+
+    for (first, size) in regions {
+        out.push(warning(
+            line,
+            format!(
+                "{} floor tiles from {},{} can not be reached from the player start or a warp",
+                size, first.x, first.y
+            ),
+        ));
+    }
+}
+
+fn props(line: usize, map: &ParsedMap, out: &mut Vec<Diagnostic>) {
+    let parsed = Map::parse_map(&map.grid, &map.max, &map.player, &map.props);
+
+    let mut ids = map.props.keys().collect::<Vec<_>>();
+    ids.sort();
+
+    for id in ids {
+        let (x, y) = &map.props[id];
+        let empty = parsed.grid.iter().any(|(point, tile)| {
+            tile.is_floor() && x.contains(&(point.x as u8)) && y.contains(&(point.y as u8))
+        });
+
+        if !empty {
+            out.push(warning(
+                line,
+                format!("props range of {} has no empty tiles to walk to", id),
+            ));
+        }
+    }
+
+    let mut npc = parsed.npc.iter().collect::<Vec<_>>();
+    npc.sort();
+
+    for (id, point) in npc {
+        let message = match map.props.get(id) {
+            Some((x, y)) if x.contains(&(point.x as u8)) && y.contains(&(point.y as u8)) => {
+                continue
+            }
+            Some(_) => format!(
+                "npc {} spawns at {},{} outside its props range",
+                id, point.x, point.y
+            ),
+            None => format!("npc {} at {},{} has no props range", id, point.x, point.y),
+        };
+
+        out.push(warning(line, message));
+    }
+}
+
+fn warps(maps: &[(usize, ParsedMap)], out: &mut Vec<Diagnostic>) {
+    let targets = |map: &ParsedMap| {
+        map.grid
+            .values()
+            .filter(|(tile, _)| Tile::from_u8(tile) == Tile::Warp)
+            .map(|(_, target)| target.to_owned())
+            .collect::<BTreeSet<_>>()
+    };
+
+    for (line, map) in maps {
+        for target in targets(map) {
+            let back = maps
+                .iter()
+                .find(|(_, other)| (other.area, other.part) == target)
+                .map(|(_, other)| targets(other).contains(&(map.area, map.part)));
+
+            if back == Some(false) {
+                out.push(warning(
+                    *line,
+                    format!(
+                        "warp to {},{} has no warp back to {},{}",
+                        target.0, target.1, map.area, map.part
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+pub fn check_maps(str: &str) -> Vec<Diagnostic> {
+    let (maps, errors) = parse_all(str);
+
+    let mut out = errors
+        .into_iter()
+        .map(|error| Diagnostic {
+            line: error.line,
+            column: error.column,
+            severity: Severity::Error,
+            message: format!("{} ({})", error.kind, error.field),
+        })
+        .collect::<Vec<_>>();
+
+    for (line, map) in &maps {
+        unreachable(*line, map, &mut out);
+        props(*line, map, &mut out);
+    }
+    warps(&maps, &mut out);
+
+    out.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    out
+}
+
+///
+/// Prints every diagnostic for `source` and returns the exit code; 1 when
+/// there are errors, 2 when there are only warnings.
+///
+pub fn run(source: &Source) -> i32 {
+    let diagnostics = check_maps(&source.text);
+
+    for diagnostic in &diagnostics {
+        let (path, line) = source.locate(diagnostic.line);
+        let severity = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        println!(
+            "{}:{}:{}: {}: {}",
+            path.display(),
+            line,
+            diagnostic.column,
+            severity,
+            diagnostic.message
+        );
+    }
+
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+
+    println!("{} errors, {} warnings", errors, warnings);
+
+    if errors > 0 {
+        1
+    } else if warnings > 0 {
+        2
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(str: &str) -> Vec<String> {
+        check_maps(str)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    #[test]
+    fn it_should_find_unreachable_floor() {
+        assert_eq!(
+            messages("0|0|7,3|0,0,0\n2,0-2|3-6,0|3-6,2|6,1"),
+            vec!["3 floor tiles from 3,1 can not be reached from the player start or a warp"]
+        );
+        assert_eq!(
+            messages("0|0|5,3|0,0,0\n2,0-2|4,1,1"),
+            vec!["6 floor tiles from 3,0 can not be reached from the player start or a warp"]
+        );
+        assert!(messages("0|0|5,3|0,0,0\n2,0-2|3,1,3,0,0").is_empty());
+    }
+
+    #[test]
+    fn it_should_find_unreachable_floor_in_visual_maps() {
+        let map = "map 0,0\nsize 5,3\nplayer 0,0,0\ngrid\n  █  \n  █  \n  ███\nend";
+        assert_eq!(
+            messages(map),
+            vec!["4 floor tiles from 3,0 can not be reached from the player start or a warp"]
+        );
+    }
+
+    #[test]
+    fn it_should_find_nothing_wrong_with_the_bundled_maps() {
+        assert_eq!(check_maps(include_str!("../../lib/maps")), vec![]);
+    }
+
+    #[test]
+    fn it_should_check_npcs_against_their_props() {
+        assert_eq!(
+            messages("0|0|5,1|0,0,0\n0,4,0|1,2-3,0\n4,0,4,0,0|1,0,4,1,0"),
+            vec![
+                "props range of 0 has no empty tiles to walk to",
+                "npc 1 spawns at 1,0 outside its props range",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_find_one_way_warps() {
+        let diagnostics = check_maps("0|0|3,1|0,0,0\n2,0,3,0,1\n0|1|3,1|0,0,0\n2,0,1");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "warp to 0,1 has no warp back to 0,0"
+        );
+    }
+}
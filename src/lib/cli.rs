@@ -0,0 +1,222 @@
+use crate::{export::Format, input::Movement, pathfinding::Neighbours};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "usage: terminal-madness [--maps <path>]... [--keys <path>]
+                        [--movement <classic|step>] [--diagonal] [--bell]
+                        [--seed <number>] [--record <path>]
+                        [--replay <path>] [--headless] [--edit <area,part>]
+       terminal-madness map-check [<path>]...
+       terminal-madness map-export [--visual] [<path>]...
+
+  --maps <path>  map file, or directory of map files, to load instead of
+                 the ones next to the binary (may be given more than once)
+  --keys <path>  key bindings to use, one \"action = key...\" per line, the
+                 default is ~/.terminal-madness.keys when it exists
+  --movement <classic|step>
+                 classic turns towards a new direction before stepping
+                 that way, step does both at once; shift+arrow always
+                 only turns
+  --diagonal     let npcs step diagonally, never across the corner of a
+                 wall
+  --bell         ring the terminal bell when walking into something
+  --seed <number>
+                 where npcs decide to walk, the same seed and the same keys
+                 play out the same way; random when left out
+  --record <path>
+                 write the seed, setup and every key pressed to a file
+                 that --replay plays back
+  --replay <path>
+                 play a recording back instead of reading keys, quit
+                 to stop watching
+  --headless     play without a terminal, the actions named one per line
+                 on stdin with \"wait <ticks>\" in between, or the replay,
+                 then print the last screen and how the game ended
+  --edit <area,part>
+                 open the map in the editor instead of playing, saving
+                 writes it back to the file it was loaded from
+
+  map-check      report errors and warnings in the given map files, or the
+                 default ones, without starting the game; exits with 1 on
+                 errors and 2 on warnings
+
+  map-export     print the given map files, or the default ones, in the
+                 range encoded format, or drawn out with --visual";
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum Mode {
+    #[default]
+    Play,
+    Check,
+    Export(Format),
+    Edit(u8, u8),
+}
+
+#[derive(Eq, PartialEq, Debug, Default)]
+pub struct Args {
+    pub mode: Mode,
+    pub maps: Vec<PathBuf>,
+    pub keys: Option<PathBuf>,
+    pub movement: Movement,
+    pub neighbours: Neighbours,
+    pub seed: Option<u64>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub headless: bool,
+    pub bell: bool,
+    pub help: bool,
+}
+
+pub fn parse_args<I>(input: I) -> Result<Args, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = Args::default();
+    let mut input = input.into_iter().peekable();
+
+    match input.peek().map(String::as_str) {
+        Some("map-check") => args.mode = Mode::Check,
+        Some("map-export") => args.mode = Mode::Export(Format::Compact),
+        _ => {}
+    }
+    if args.mode != Mode::Play {
+        input.next();
+    }
+
+    while let Some(arg) = input.next() {
+        match arg.as_str() {
+            "--maps" => match input.next() {
+                Some(path) => args.maps.push(PathBuf::from(path)),
+                None => return Err("--maps needs a path".to_string()),
+            },
+            "--visual" if matches!(args.mode, Mode::Export(_)) => {
+                args.mode = Mode::Export(Format::Visual)
+            }
+            "--keys" => match input.next() {
+                Some(path) => args.keys = Some(PathBuf::from(path)),
+                None => return Err("--keys needs a path".to_string()),
+            },
+            "--movement" => match input.next().as_deref().and_then(Movement::from_name) {
+                Some(movement) => args.movement = movement,
+                None => return Err("--movement needs classic or step".to_string()),
+            },
+            "--diagonal" => args.neighbours = Neighbours::Eight,
+            "--bell" => args.bell = true,
+            "--seed" => match input.next().map(|seed| seed.parse::<u64>()) {
+                Some(Ok(seed)) => args.seed = Some(seed),
+                _ => return Err("--seed needs a number".to_string()),
+            },
+            "--record" => match input.next() {
+                Some(path) => args.record = Some(PathBuf::from(path)),
+                None => return Err("--record needs a path".to_string()),
+            },
+            "--replay" => match input.next() {
+                Some(path) => args.replay = Some(PathBuf::from(path)),
+                None => return Err("--replay needs a path".to_string()),
+            },
+            "--headless" => args.headless = true,
+            "--edit" if args.mode == Mode::Play => {
+                let key = input.next().unwrap_or_default();
+                let mut values = key.splitn(2, ',').map(|value| value.trim().parse::<u8>());
+                match (values.next(), values.next()) {
+                    (Some(Ok(area)), Some(Ok(part))) => args.mode = Mode::Edit(area, part),
+                    _ => return Err("--edit needs a map as area,part".to_string()),
+                }
+            }
+            "-h" | "--help" => args.help = true,
+            path if args.mode != Mode::Play && !path.starts_with('-') => {
+                args.maps.push(PathBuf::from(path))
+            }
+            _ => return Err(format!("unknown argument \"{}\"", arg)),
+        }
+    }
+
+    if args.record.is_some() && args.replay.is_some() {
+        return Err("--record and --replay can't be used together".to_string());
+    }
+    if args.headless && args.record.is_some() {
+        return Err("--headless can't be recorded".to_string());
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(input: &[&str]) -> Result<Args, String> {
+        parse_args(input.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn it_should_collect_every_maps_path() {
+        assert_eq!(
+            args(&["--maps", "a", "--maps", "b/"]).unwrap().maps,
+            vec![PathBuf::from("a"), PathBuf::from("b/")]
+        );
+    }
+
+    #[test]
+    fn it_should_reject_missing_values_and_unknown_flags() {
+        assert!(args(&["--maps"]).is_err());
+        assert!(args(&["--map", "a"]).is_err());
+        assert!(args(&["a"]).is_err());
+    }
+
+    #[test]
+    fn it_should_take_paths_for_map_check() {
+        let args = args(&["map-check", "a", "--maps", "b"]).unwrap();
+        assert_eq!(args.mode, Mode::Check);
+        assert_eq!(args.maps, vec![PathBuf::from("a"), PathBuf::from("b")]);
+    }
+
+    #[test]
+    fn it_should_pick_the_export_format() {
+        assert_eq!(
+            args(&["map-export", "a"]).unwrap().mode,
+            Mode::Export(Format::Compact)
+        );
+        assert_eq!(
+            args(&["map-export", "--visual"]).unwrap().mode,
+            Mode::Export(Format::Visual)
+        );
+        assert!(args(&["--visual"]).is_err());
+    }
+
+    #[test]
+    fn it_should_take_the_movement_mode() {
+        assert_eq!(args(&[]).unwrap().movement, Movement::Classic);
+        assert_eq!(
+            args(&["--movement", "step"]).unwrap().movement,
+            Movement::Step
+        );
+        assert!(args(&["--movement", "run"]).is_err());
+        assert_eq!(args(&["--diagonal"]).unwrap().neighbours, Neighbours::Eight);
+        assert!(args(&["--bell"]).unwrap().bell);
+    }
+
+    #[test]
+    fn it_should_take_a_seed() {
+        assert_eq!(args(&["--seed", "42"]).unwrap().seed, Some(42));
+        assert_eq!(args(&[]).unwrap().seed, None);
+        assert!(args(&["--seed", "-1"]).is_err());
+    }
+
+    #[test]
+    fn it_should_record_or_replay() {
+        let replay = args(&["--replay", "run.log", "--headless"]).unwrap();
+        assert_eq!(replay.replay, Some(PathBuf::from("run.log")));
+        assert!(replay.headless);
+
+        assert!(args(&["--record", "a", "--replay", "b"]).is_err());
+        assert!(args(&["--record", "a", "--headless"]).is_err());
+        assert!(args(&["--headless"]).unwrap().headless);
+    }
+
+    #[test]
+    fn it_should_take_the_map_to_edit() {
+        assert_eq!(args(&["--edit", "0,1"]).unwrap().mode, Mode::Edit(0, 1));
+        assert!(args(&["--edit", "0"]).is_err());
+        assert!(args(&["--edit"]).is_err());
+    }
+}
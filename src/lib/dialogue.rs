@@ -0,0 +1,207 @@
+use crate::{
+    flags::{parse_flags, Flag},
+    helper::{unwrap_and_parse, unwrap_or},
+};
+use std::collections::HashMap;
+
+pub type Speaker = (u8, u8, u8);
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Choice {
+    pub target: u8,
+    pub text: String,
+    pub flags: Vec<Flag>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Node {
+    pub text: String,
+    pub next: Vec<u8>,
+    pub flags: Vec<Flag>,
+    pub choices: Vec<Choice>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Dialogue {
+    pub start: u8,
+    pub nodes: HashMap<u8, Node>,
+}
+
+impl Dialogue {
+    pub fn get(&self, node: &u8) -> Option<&Node> {
+        self.nodes.get(node)
+    }
+}
+
+///
+/// One node per line, `id,area,part|node|next|flags|text`, where `id` is the
+/// first value of the NPC tile meta in `lib/maps`. `next` lists the nodes to
+/// continue to, the first one whose conditions hold wins, and an empty `next`
+/// ends the dialogue. The first node listed for a speaker is where the
+/// dialogue starts; a node without text just passes on to its `next`.
+///
+/// A `next` of `>target` adds a numbered choice leading to `target` to the
+/// node instead, see `flags::parse_flags` for the `flags` field.
+///
+pub fn parse_dialogues(str: &str) -> HashMap<Speaker, Dialogue> {
+    let mut dialogues: HashMap<Speaker, Dialogue> = HashMap::new();
+
+    for line in str.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let mut fields = line.splitn(5, '|');
+
+        let mut speaker = unwrap_or(&mut fields, "0,0,0").split(',');
+        let speaker = (
+            unwrap_and_parse(&mut speaker, "0", 0),
+            unwrap_and_parse(&mut speaker, "0", 0),
+            unwrap_and_parse(&mut speaker, "0", 0),
+        );
+
+        let node = unwrap_and_parse(&mut fields, "0", 0);
+        let next = unwrap_or(&mut fields, "");
+        let flags = parse_flags(unwrap_or(&mut fields, ""));
+        let text = unwrap_or(&mut fields, "").to_string();
+
+        let dialogue = dialogues.entry(speaker).or_insert_with(|| Dialogue {
+            start: node,
+            nodes: HashMap::new(),
+        });
+        let node = dialogue.nodes.entry(node).or_default();
+
+        if let Some(target) = next.strip_prefix('>') {
+            node.choices.push(Choice {
+                target: target.parse().unwrap_or(0),
+                text,
+                flags,
+            });
+            continue;
+        }
+
+        node.text = text;
+        node.flags = flags;
+        node.next = next
+            .split(',')
+            .filter_map(|next| next.trim().parse::<u8>().ok())
+            .collect();
+    }
+
+    dialogues
+}
+
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let len = line.chars().count();
+        if len > 0 && len + 1 + word.chars().count() > width {
+            lines.push(line);
+            line = String::new();
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+pub fn dialogue_box(text: &str, choices: &[&str], selected: usize, width: usize) -> Vec<String> {
+    let hint = if choices.is_empty() {
+        "[enter]".to_string()
+    } else {
+        format!("[1-{}]", choices.len())
+    };
+    let inner = width.max(hint.chars().count() + 4) - 4;
+    let pad = |line: &str| {
+        let len = line.chars().count();
+        format!("│ {}{} │", line, " ".repeat(inner.saturating_sub(len)))
+    };
+
+    let mut out = vec![format!("┌{}┐", "─".repeat(inner + 2))];
+    for line in wrap(text, inner) {
+        out.push(pad(&line));
+    }
+    for (i, choice) in choices.iter().enumerate() {
+        let marker = if i == selected { '>' } else { ' ' };
+        for (j, line) in wrap(choice, inner.saturating_sub(5)).iter().enumerate() {
+            out.push(pad(&match j {
+                0 => format!("{} {}. {}", marker, i + 1, line),
+                _ => format!("     {}", line),
+            }));
+        }
+    }
+    out.push(pad(&format!("{:>1$}", hint, inner)));
+    out.push(format!("└{}┘", "─".repeat(inner + 2)));
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_parse_nodes_per_speaker() {
+        let dialogues = parse_dialogues(
+            "
+            0,0,0|0|1||Hello there.
+            0,0,0|1|||Bye.
+            1,0,1|3|||Go away.
+            ",
+        );
+
+        let first = dialogues.get(&(0, 0, 0)).unwrap();
+        assert_eq!(first.start, 0);
+        assert_eq!(first.get(&0).unwrap().next, vec![1]);
+        assert!(first.get(&1).unwrap().next.is_empty());
+        assert_eq!(first.get(&1).unwrap().text, "Bye.");
+
+        let second = dialogues.get(&(1, 0, 1)).unwrap();
+        assert_eq!(second.start, 3);
+        assert_eq!(second.get(&3).unwrap().text, "Go away.");
+    }
+
+    #[test]
+    fn it_should_keep_pipes_in_text() {
+        let dialogues = parse_dialogues("0,0,0|0|||a | b");
+        assert_eq!(dialogues[&(0, 0, 0)].get(&0).unwrap().text, "a | b");
+    }
+
+    #[test]
+    fn it_should_attach_choices_and_flags_to_nodes() {
+        let dialogues = parse_dialogues(
+            "
+            0,0,0|0|2,1||
+            0,0,0|1||!gate|Want in?
+            0,0,0|1|>2|+gate|Yes.
+            0,0,0|1|>3||No.
+            0,0,0|2||?gate|Welcome.
+            ",
+        );
+
+        let dialogue = &dialogues[&(0, 0, 0)];
+        assert!(dialogue.get(&0).unwrap().text.is_empty());
+        assert_eq!(dialogue.get(&0).unwrap().next, vec![2, 1]);
+
+        let node = dialogue.get(&1).unwrap();
+        assert_eq!(node.flags, vec![Flag::Forbids("gate".to_string())]);
+        assert_eq!(node.choices.len(), 2);
+        assert_eq!(node.choices[0].target, 2);
+        assert_eq!(node.choices[0].text, "Yes.");
+        assert_eq!(node.choices[0].flags, vec![Flag::Set("gate".to_string())]);
+    }
+
+    #[test]
+    fn it_should_wrap_text_to_width() {
+        assert_eq!(
+            wrap("the quick brown fox jumps", 10),
+            vec!["the quick", "brown fox", "jumps"]
+        );
+    }
+}
@@ -0,0 +1,529 @@
+use crate::{
+    camera::{needed, too_small, Camera},
+    export::{to_compact, to_visual, Format},
+    files::Source,
+    helper::{parse_all, parse_blocks, Field, MapErrorKind, Parser, Token},
+    input::Key,
+    map::Map,
+    render::{Frame, Renderer, Terminal},
+    shared::{Direction, ParsedMap, Point, Tile},
+    visual::is_visual,
+};
+use std::{collections::HashSet, fs, path::PathBuf};
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum Prompt {
+    Warp,
+    Npc,
+    Props,
+    Resize,
+}
+
+impl Prompt {
+    fn label(&self) -> &str {
+        match self {
+            Prompt::Warp => "warp to area,part",
+            Prompt::Npc => "npc id,static (0 walks, 1 stands)",
+            Prompt::Props => "props id,x-x,y-y",
+            Prompt::Resize => "size width,height",
+        }
+    }
+}
+
+const HELP: [&str; 2] = [
+    "0 wall 1 floor 2 start 3 warp 4 npc 5 grass 6 mud",
+    "f face  p props  r size  ^S save",
+];
+
+///
+/// Edits one map in place and writes it back to the file it was loaded from,
+/// in the format it was in, leaving the rest of that file as it was.
+///
+pub struct Editor {
+    map: ParsedMap,
+    cursor: Point,
+    prompt: Option<(Prompt, String)>,
+    status: String,
+    path: Option<PathBuf>,
+    file: Vec<String>,
+    lines: (usize, usize),
+    format: Format,
+    keys: HashSet<(u8, u8)>,
+    renderer: Box<dyn Renderer>,
+}
+
+impl Editor {
+    pub fn new(source: &Source, key: (u8, u8)) -> Result<Editor, String> {
+        let (blocks, errors) = parse_blocks(&source.text);
+        if let Some(error) = errors.first() {
+            return Err(source.describe(error));
+        }
+
+        let block = match blocks
+            .iter()
+            .find(|block| (block.map.area, block.map.part) == key)
+        {
+            Some(block) => block,
+            None => return Err(format!("there is no map {},{} to edit", key.0, key.1)),
+        };
+
+        let (path, first) = source.locate(block.lines.0);
+        let (path, last) = (path.to_owned(), source.locate(block.lines.1).1);
+        let file = source
+            .text
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| source.locate(i + 1).0 == path)
+            .map(|(_, line)| line.to_string())
+            .collect::<Vec<_>>();
+
+        let format = match is_visual(&file[first - 1]) {
+            true => Format::Visual,
+            false => Format::Compact,
+        };
+        let map = block.map.to_owned();
+
+        Ok(Editor {
+            cursor: Point {
+                x: map.player.0,
+                y: map.player.1,
+            },
+            map,
+            prompt: None,
+            status: String::new(),
+            path: Some(path).filter(|_| !source.is_embedded()),
+            file,
+            lines: (first, last),
+            format,
+            keys: blocks
+                .iter()
+                .map(|block| (block.map.area, block.map.part))
+                .collect(),
+            renderer: Box::new(Terminal::default()),
+        })
+    }
+
+    ///
+    /// Draws to `renderer` from now on.
+    ///
+    pub fn set_renderer(&mut self, renderer: Box<dyn Renderer>) {
+        self.renderer = renderer;
+        self.render();
+    }
+
+    pub fn move_cursor(&mut self, direction: &Direction) {
+        let (max_x, max_y) = self.map.max;
+        let cursor = &mut self.cursor;
+        match direction {
+            Direction::Up => cursor.y = cursor.y.saturating_sub(1),
+            Direction::Down => cursor.y = (cursor.y + 1).min(max_y.saturating_sub(1)),
+            Direction::Right => cursor.x = (cursor.x + 1).min(max_x.saturating_sub(1)),
+            Direction::Left => cursor.x = cursor.x.saturating_sub(1),
+        }
+        self.render();
+    }
+
+    pub fn input(&mut self, key: Key) {
+        self.status.clear();
+
+        if let Some((prompt, mut text)) = self.prompt.take() {
+            match key {
+                Key::Enter => match self.submit(prompt, &text) {
+                    Ok(()) => {}
+                    Err(error) => {
+                        self.status = error;
+                        self.prompt = Some((prompt, text));
+                    }
+                },
+                Key::Esc => {}
+                Key::Backspace => {
+                    text.pop();
+                    self.prompt = Some((prompt, text));
+                }
+                Key::Char(char) => {
+                    text.push(char);
+                    self.prompt = Some((prompt, text));
+                }
+                _ => self.prompt = Some((prompt, text)),
+            }
+            self.render();
+            return;
+        }
+
+        let (x, y) = (self.cursor.x, self.cursor.y);
+        let under = self.map.grid.get(&self.cursor).cloned();
+        let meta = |tile: u8| match under {
+            Some((code, meta)) if code == tile => format!("{},{}", meta.0, meta.1),
+            _ => String::new(),
+        };
+
+        match key {
+            Key::Char('0') => self.paint(Some((0, (0, 0)))),
+            Key::Char('1') => self.paint(None),
+            Key::Char('2') => self.set_start(),
+            Key::Char('3') => self.prompt = Some((Prompt::Warp, meta(3))),
+            Key::Char('4') => self.prompt = Some((Prompt::Npc, meta(4))),
+            Key::Char('5') => self.paint(Some((5, (0, 0)))),
+            Key::Char('6') => self.paint(Some((6, (0, 0)))),
+            Key::Char('p') => {
+                let id = match under {
+                    Some((4, (id, _))) => id,
+                    _ => 0,
+                };
+                let text = match self.map.props.get(&id) {
+                    Some((x, y)) => {
+                        format!("{},{}-{},{}-{}", id, x.start(), x.end(), y.start(), y.end())
+                    }
+                    None => format!("{},{},{}", id, x, y),
+                };
+                self.prompt = Some((Prompt::Props, text));
+            }
+            Key::Char('r') => {
+                let text = format!("{},{}", self.map.max.0, self.map.max.1);
+                self.prompt = Some((Prompt::Resize, text));
+            }
+            Key::Char('f') => self.map.player.2 = (self.map.player.2 + 1) % 4,
+            Key::Ctrl('s') => {
+                self.status = match self.save() {
+                    Ok(path) => format!("saved to {}", path.display()),
+                    Err(error) => error,
+                }
+            }
+            _ => {}
+        }
+
+        self.render();
+    }
+
+    fn is_start(&self) -> bool {
+        self.cursor.x == self.map.player.0 && self.cursor.y == self.map.player.1
+    }
+
+    fn paint(&mut self, tile: Option<(u8, (u8, u8))>) {
+        match tile {
+            Some(_) if self.is_start() => {
+                self.status = format!("the player starts at {},{}", self.cursor.x, self.cursor.y);
+            }
+            Some(tile) => {
+                self.map.grid.insert(self.cursor, tile);
+            }
+            None => {
+                self.map.grid.remove(&self.cursor);
+            }
+        }
+    }
+
+    fn set_start(&mut self) {
+        self.map.grid.remove(&self.cursor);
+        self.map.player.0 = self.cursor.x;
+        self.map.player.1 = self.cursor.y;
+    }
+
+    fn submit(&mut self, prompt: Prompt, text: &str) -> Result<(), String> {
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+
+        let mut parser = Parser {
+            line: 1,
+            field: Field::Tiles,
+            errors: Vec::new(),
+        };
+        let token = Token::line(text);
+
+        match prompt {
+            Prompt::Warp | Prompt::Npc => {
+                let names = match prompt {
+                    Prompt::Warp => ["area", "part"],
+                    _ => ["id", "part"],
+                };
+                let values = parser.values(&token, ',', &names, 1);
+                let values = values.and_then(|values| {
+                    let first = parser.number::<u8>(&values[0]);
+                    let second = match values.get(1) {
+                        Some(value) => parser.number::<u8>(value),
+                        None => Some(0),
+                    };
+                    Some((first?, second?))
+                });
+
+                if let Some(meta) = values {
+                    if prompt == Prompt::Warp && !self.keys.contains(&meta) {
+                        parser.error(token.column, MapErrorKind::WarpTarget(meta.0, meta.1));
+                    } else {
+                        let tile = if prompt == Prompt::Warp { 3 } else { 4 };
+                        self.paint(Some((tile, meta)));
+                    }
+                }
+            }
+            Prompt::Props => {
+                parser.field = Field::Props;
+                let values = parser.values(&token, ',', &["id", "x", "y"], 3);
+                let values = values.and_then(|values| {
+                    let id = parser.number::<u8>(&values[0]);
+                    let (x, y) = (
+                        parser.range::<u8>(&values[1]),
+                        parser.range::<u8>(&values[2]),
+                    );
+                    Some((id?, x?, y?))
+                });
+
+                if let Some((id, x, y)) = values {
+                    let (max_x, max_y) = self.map.max;
+                    if x.0.max(x.1) as usize >= max_x || y.0.max(y.1) as usize >= max_y {
+                        parser.error(token.column, MapErrorKind::PropsOutside(id));
+                    } else {
+                        self.map.props.insert(id, (x.0..=x.1, y.0..=y.1));
+                    }
+                }
+            }
+            Prompt::Resize => {
+                parser.field = Field::Header;
+                let values = parser.values(&token, ',', &["width", "height"], 2);
+                let values = values.and_then(|values| {
+                    let (x, y) = (parser.number(&values[0]), parser.number(&values[1]));
+                    Some((x?, y?))
+                });
+
+                if let Some(max) = values {
+                    self.resize(max);
+                }
+            }
+        }
+
+        match parser.errors.first() {
+            Some(error) => Err(error.kind.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    ///
+    /// Drops tiles that no longer fit, and cuts props ranges down to the new
+    /// size; the player start and cursor are moved inside if they fall out.
+    ///
+    fn resize(&mut self, max: (usize, usize)) {
+        let (x, y) = (max.0.clamp(1, 256), max.1.clamp(1, 256));
+        let map = &mut self.map;
+
+        map.max = (x, y);
+        map.grid.retain(|point, _| point.x < x && point.y < y);
+        map.props
+            .retain(|_, range| (*range.0.start() as usize) < x && (*range.1.start() as usize) < y);
+        for (range_x, range_y) in map.props.values_mut() {
+            *range_x = *range_x.start()..=*range_x.end().min(&((x - 1) as u8));
+            *range_y = *range_y.start()..=*range_y.end().min(&((y - 1) as u8));
+        }
+
+        map.player.0 = map.player.0.min(x - 1);
+        map.player.1 = map.player.1.min(y - 1);
+        map.grid.remove(&Point {
+            x: map.player.0,
+            y: map.player.1,
+        });
+
+        self.cursor.x = self.cursor.x.min(x - 1);
+        self.cursor.y = self.cursor.y.min(y - 1);
+    }
+
+    ///
+    /// The file the map came from with only the lines of the map replaced.
+    ///
+    fn file_text(&self) -> String {
+        let map = match self.format {
+            Format::Compact => to_compact(&self.map),
+            Format::Visual => to_visual(&self.map),
+        };
+
+        let (before, after) = (&self.file[..self.lines.0 - 1], &self.file[self.lines.1..]);
+        let lines = before
+            .iter()
+            .map(String::as_str)
+            .chain(map.lines())
+            .chain(after.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+
+        lines.join("\n") + "\n"
+    }
+
+    ///
+    /// Checks the file parses again before writing it; warps may lead to maps
+    /// in other files, so only targets missing everywhere are errors.
+    ///
+    pub fn save(&mut self) -> Result<PathBuf, String> {
+        let text = self.file_text();
+        let (_, errors) = parse_all(&text);
+        let error = errors.iter().find(|error| match error.kind {
+            MapErrorKind::WarpTarget(area, part) => !self.keys.contains(&(area, part)),
+            _ => true,
+        });
+
+        if let Some(error) = error {
+            return Err(error.kind.to_string());
+        }
+
+        let path = match &self.path {
+            Some(path) => path.to_owned(),
+            None => {
+                return Err("these maps are built in, open a file with --maps to save".to_string())
+            }
+        };
+
+        fs::write(&path, text).map_err(|error| error.to_string())?;
+        Ok(path)
+    }
+
+    pub fn redraw(&mut self) {
+        self.renderer.invalidate();
+        self.render();
+    }
+
+    pub fn render(&mut self) {
+        let map = &self.map;
+        let tile = match map.grid.get(&self.cursor) {
+            _ if self.is_start() => "start".to_string(),
+            Some((code, meta)) => match Tile::from_u8(code) {
+                Tile::Warp => format!("warp to {},{}", meta.0, meta.1),
+                Tile::NPC => format!("npc {},{}", meta.0, meta.1),
+                tile => format!("{:?}", tile).to_lowercase(),
+            },
+            None => "floor".to_string(),
+        };
+
+        let mut below = vec![format!(
+            "map {},{}  size {},{}  {},{}: {}",
+            map.area, map.part, map.max.0, map.max.1, self.cursor.x, self.cursor.y, tile
+        )];
+        below.extend(HELP.iter().map(|line| line.to_string()));
+        if let Some((prompt, text)) = &self.prompt {
+            below.push(format!("{}: {}", prompt.label(), text));
+        }
+        if !self.status.is_empty() {
+            below.push(self.status.to_owned());
+        }
+
+        // the help is wider than most maps, so it isn't centered with the grid
+        let screen = self.renderer.size();
+        let need = needed(map.max, Frame::new(&below).size());
+        if let Some(notice) = too_small(screen, need) {
+            self.renderer.draw(&notice);
+            return;
+        }
+
+        let view = (screen.0, screen.1 - below.len());
+        let camera = Camera::follow(map.max, &self.cursor, view);
+        let map = Map::parse_map(&map.grid, &map.max, &map.player, &map.props);
+
+        let mut lines = map.grid_lines(&camera, &[]);
+        lines.extend(below);
+        self.renderer.draw(&lines);
+
+        if let Some((x, y)) = camera.on_screen(&self.cursor) {
+            self.renderer.show_cursor(x, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Buffer;
+    use std::path::Path;
+
+    const MAPS: &str = "0|0|5,3|1,1,0\n0-4,0|0,1|4,1|0-4,2\n0|1|3,3|1,1,0\n1,0,3,0,0";
+
+    fn editor() -> Editor {
+        let mut editor = Editor::new(&Source::new(Path::new("maps"), MAPS), (0, 0)).unwrap();
+        editor.set_renderer(Box::new(Buffer::default()));
+        editor
+    }
+
+    fn type_in(editor: &mut Editor, text: &str) {
+        while editor.prompt.iter().any(|(_, text)| !text.is_empty()) {
+            editor.input(Key::Backspace);
+        }
+        for char in text.chars() {
+            editor.input(Key::Char(char));
+        }
+        editor.input(Key::Enter);
+    }
+
+    #[test]
+    fn it_should_paint_tiles_and_write_them_back() {
+        let mut editor = editor();
+        editor.move_cursor(&Direction::Right);
+        editor.input(Key::Char('4'));
+        type_in(&mut editor, "2");
+        editor.move_cursor(&Direction::Right);
+        editor.input(Key::Char('3'));
+        type_in(&mut editor, "0,1");
+
+        assert_eq!(
+            editor.file_text(),
+            "0|0|5,3|1,1,0\n0-4,0|0,1-2|2,1,4,2,0|3,1,3,0,1|4,1-2|1-3,2\n0|1|3,3|1,1,0\n1,0,3,0,0\n"
+        );
+        assert!(editor.prompt.is_none());
+    }
+
+    #[test]
+    fn it_should_keep_the_prompt_open_on_bad_input() {
+        let mut editor = editor();
+        editor.input(Key::Char('3'));
+        type_in(&mut editor, "7,7");
+
+        assert_eq!(editor.status, "warp to 7,7 which does not exist");
+        assert!(editor.prompt.is_some());
+        assert_eq!(editor.map.grid.get(&editor.cursor), None);
+    }
+
+    #[test]
+    fn it_should_drop_what_no_longer_fits_when_resizing() {
+        let mut editor = editor();
+        editor.input(Key::Char('p'));
+        type_in(&mut editor, "0,0-4,0-2");
+        editor.input(Key::Char('r'));
+        type_in(&mut editor, "3,2");
+
+        let map = &editor.map;
+        assert_eq!(map.max, (3, 2));
+        assert_eq!(map.props.get(&0), Some(&(0..=2, 0..=1)));
+        assert!(map.grid.keys().all(|point| point.x < 3 && point.y < 2));
+        assert!(parse_all(&editor.file_text()).1.is_empty());
+    }
+
+    #[test]
+    fn it_should_leave_the_other_maps_in_the_file_alone() {
+        let others = [
+            "0|1|3,3|1,1,0",
+            "1,0,3,0,0|1,1,1|2,1,1",
+            "",
+            "",
+            "map 0,2",
+            "size 3,1",
+            "player 0,0,0",
+            "grid",
+            "   ",
+            "end",
+        ];
+        let text = format!(
+            "\n0|0|5,3|1,1,0\n0-4,0|0,1-2|4,1-2|1-3,2\n\n{}\n",
+            others.join("\n")
+        );
+        let source = Source::new(Path::new("maps"), &text);
+        let mut editor = Editor::new(&source, (0, 0)).unwrap();
+        editor.set_renderer(Box::new(Buffer::default()));
+        editor.move_cursor(&Direction::Right);
+        editor.input(Key::Char('5'));
+
+        let saved = editor.file_text();
+        assert_eq!(saved, text.replacen("|4,1-2|", "|2,1,5|4,1-2|", 1));
+        assert!(parse_all(&saved).1.is_empty());
+    }
+
+    #[test]
+    fn it_should_not_save_the_built_in_maps() {
+        let source = Source::embedded(Path::new("lib/maps"), MAPS);
+        let mut editor = Editor::new(&source, (0, 0)).unwrap();
+
+        assert!(editor.save().unwrap_err().contains("--maps"));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{game::Game, input::Action, messages::Severity, shared::Point};
+use std::io::{stdout, Write};
+
+///
+/// What the game is told to do, by the keyboard, a replay or a test.
+///
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Command {
+    Act(Action),
+    Resize,
+}
+
+///
+/// What came of a command or a tick, for whoever subscribes to hear it.
+/// `Changed` comes last whenever anything on screen did, `Resized` whenever
+/// the screen has to be drawn over from scratch.
+///
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Event {
+    PlayerMoved {
+        from: Point,
+        to: Point,
+    },
+    Warped {
+        from: (u8, u8),
+        to: (u8, u8),
+    },
+    NpcMoved {
+        id: u8,
+        from: Point,
+        to: Point,
+    },
+    Interacted {
+        at: Point,
+        npc: Option<u8>,
+    },
+    Blocked {
+        at: Point,
+    },
+    Said {
+        ticks: u64,
+        severity: Severity,
+        text: String,
+    },
+    Changed,
+    Resized,
+}
+
+///
+/// Hears every event along with the game as it is once the tick is over, to
+/// look up whatever else it needs.
+///
+pub trait Subscriber {
+    fn notify(&mut self, event: &Event, game: &Game);
+}
+
+impl<F: FnMut(&Event)> Subscriber for F {
+    fn notify(&mut self, event: &Event, _: &Game) {
+        self(event)
+    }
+}
+
+///
+/// Holds on to events as they happen and hands them to every subscriber
+/// together, in the order they happened, once the game is done with a tick.
+/// Each event goes to those that had subscribed when it was emitted.
+///
+#[derive(Default)]
+pub struct Bus {
+    subscribers: Vec<Box<dyn Subscriber>>,
+    queue: Vec<(Event, usize)>,
+}
+
+impl Bus {
+    ///
+    /// Adds `subscriber` to those handed events by `publish`, starting with
+    /// the next one emitted.
+    ///
+    pub fn subscribe(&mut self, subscriber: Box<dyn Subscriber>) {
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn emit(&mut self, event: Event) {
+        self.queue.push((event, self.subscribers.len()));
+    }
+
+    ///
+    /// Hands out everything emitted since the last call, and returns it.
+    ///
+    pub fn publish(&mut self, game: &Game) -> Vec<Event> {
+        let queue = self.queue.drain(..).collect::<Vec<_>>();
+        for (event, heard_by) in &queue {
+            for subscriber in self.subscribers[..*heard_by].iter_mut() {
+                subscriber.notify(event, game);
+            }
+        }
+
+        queue.into_iter().map(|(event, _)| event).collect()
+    }
+}
+
+///
+/// Rings the terminal bell whenever the player walks into something.
+///
+pub struct Bell;
+
+impl Subscriber for Bell {
+    fn notify(&mut self, event: &Event, _: &Game) {
+        if let Event::Blocked { .. } = event {
+            print!("\x07");
+            let _ = stdout().flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splash::Screens;
+    use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+    fn game() -> Game {
+        Game::new(
+            Vec::new(),
+            HashMap::new(),
+            HashMap::new(),
+            Screens::default(),
+        )
+    }
+
+    #[test]
+    fn it_should_hand_events_to_every_subscriber_once_published() {
+        let heard = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = Bus::default();
+        for name in &["a", "b"] {
+            let heard = heard.clone();
+            bus.subscribe(Box::new(move |event: &Event| {
+                heard.borrow_mut().push((*name, event.to_owned()))
+            }));
+        }
+
+        let blocked = Event::Blocked {
+            at: Point { x: 1, y: 4 },
+        };
+        let warped = Event::Warped {
+            from: (0, 0),
+            to: (0, 1),
+        };
+        bus.emit(blocked.to_owned());
+        bus.emit(warped.to_owned());
+        assert!(heard.borrow().is_empty());
+
+        let game = game();
+        assert_eq!(
+            bus.publish(&game),
+            vec![blocked.to_owned(), warped.to_owned()]
+        );
+        assert_eq!(
+            *heard.borrow(),
+            vec![
+                ("a", blocked.to_owned()),
+                ("b", blocked),
+                ("a", warped.to_owned()),
+                ("b", warped)
+            ]
+        );
+        assert!(bus.publish(&game).is_empty());
+    }
+
+    #[test]
+    fn it_should_only_hand_a_late_subscriber_what_came_after() {
+        let heard = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = Bus::default();
+        let (before, after) = (
+            Event::Blocked {
+                at: Point { x: 2, y: 3 },
+            },
+            Event::Blocked {
+                at: Point { x: 3, y: 3 },
+            },
+        );
+        bus.emit(before.to_owned());
+
+        let late = heard.clone();
+        bus.subscribe(Box::new(move |event: &Event| {
+            late.borrow_mut().push(event.to_owned())
+        }));
+        bus.emit(after.to_owned());
+
+        assert_eq!(bus.publish(&game()), vec![before, after.to_owned()]);
+        assert_eq!(*heard.borrow(), vec![after]);
+    }
+}
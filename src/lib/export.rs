@@ -0,0 +1,230 @@
+use crate::{
+    shared::{ParsedMap, Point, Tile},
+    visual::{GLYPH_EMPTY, GLYPH_GRASS, GLYPH_MUD, GLYPH_NPC, GLYPH_WALL, GLYPH_WARP},
+};
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Format {
+    Compact,
+    Visual,
+}
+
+fn range<T: PartialEq + ToString>(start: T, end: T) -> String {
+    if start == end {
+        start.to_string()
+    } else {
+        format!("{}-{}", start.to_string(), end.to_string())
+    }
+}
+
+fn sorted_props(map: &ParsedMap) -> Vec<String> {
+    let props = map.props.iter().collect::<BTreeMap<_, _>>();
+    props
+        .iter()
+        .map(|(id, (x, y))| {
+            format!(
+                "{},{},{}",
+                id,
+                range(x.start(), x.end()),
+                range(y.start(), y.end())
+            )
+        })
+        .collect()
+}
+
+type Rectangle = (Point, Point, (u8, (u8, u8)));
+
+///
+/// Covers the tiles with as few rectangles as a greedy pass finds; each one
+/// grows right along its first row, then down for as long as whole rows of
+/// the same tile and meta follow.
+///
+fn rectangles(map: &ParsedMap) -> Vec<Rectangle> {
+    let grid = map.grid.iter().collect::<BTreeMap<_, _>>();
+    let mut used = HashSet::new();
+    let mut out = Vec::new();
+
+    for (start, value) in &grid {
+        if used.contains(*start) {
+            continue;
+        }
+
+        let same = |point: &Point| !used.contains(point) && grid.get(point) == Some(value);
+
+        let mut end = **start;
+        while same(&Point {
+            x: end.x + 1,
+            y: end.y,
+        }) {
+            end.x += 1;
+        }
+
+        while (start.x..=end.x).all(|x| same(&Point { x, y: end.y + 1 })) {
+            end.y += 1;
+        }
+
+        for x in start.x..=end.x {
+            for y in start.y..=end.y {
+                used.insert(Point { x, y });
+            }
+        }
+
+        out.push((**start, end, **value));
+    }
+
+    out
+}
+
+///
+/// The range encoded format `helper::parse_maps` reads; the props line is
+/// left out when there are no props.
+///
+pub fn to_compact(map: &ParsedMap) -> String {
+    let header = format!(
+        "{}|{}|{},{}|{},{},{}",
+        map.area, map.part, map.max.0, map.max.1, map.player.0, map.player.1, map.player.2
+    );
+
+    let mut tiles = rectangles(map)
+        .into_iter()
+        .map(|(start, end, (tile, meta))| {
+            let mut out = format!("{},{}", range(start.x, end.x), range(start.y, end.y));
+            if tile != 0 || meta != (0, 0) {
+                out.push_str(&format!(",{}", tile));
+            }
+            if meta != (0, 0) {
+                out.push_str(&format!(",{},{}", meta.0, meta.1));
+            }
+            out
+        })
+        .collect::<Vec<_>>();
+
+    // a map needs a tile line, so spell out the floor under the player
+    if tiles.is_empty() {
+        tiles.push(format!("{},{},1", map.player.0, map.player.1));
+    }
+
+    let mut lines = vec![header];
+    if !map.props.is_empty() {
+        lines.push(sorted_props(map).join("|"));
+    }
+    lines.push(tiles.join("|"));
+
+    lines.join("\n")
+}
+
+///
+/// The format `visual::parse_visual` reads. Tiles that can't be drawn, like
+/// floor given explicitly, come out as plain floor.
+///
+pub fn to_visual(map: &ParsedMap) -> String {
+    let mut lines = vec![
+        format!("map {},{}", map.area, map.part),
+        format!("size {},{}", map.max.0, map.max.1),
+        format!("player {},{},{}", map.player.0, map.player.1, map.player.2),
+    ];
+
+    for props in sorted_props(map) {
+        let mut values = props.splitn(2, ',');
+        let (id, ranges) = (values.next().unwrap(), values.next().unwrap());
+        lines.push(format!("props {} {}", id, ranges));
+    }
+
+    let grid = map.grid.iter().collect::<BTreeMap<_, _>>();
+    let with = |tile: Tile| {
+        grid.iter()
+            .filter(move |(_, (code, _))| Tile::from_u8(code) == tile)
+            .map(|(point, (_, meta))| (point.to_owned(), meta.to_owned()))
+    };
+
+    for (point, meta) in with(Tile::Warp) {
+        lines.push(format!(
+            "warp {},{} {},{}",
+            point.x, point.y, meta.0, meta.1
+        ));
+    }
+
+    lines.push("grid".to_string());
+    for y in 0..map.max.1 {
+        let row = (0..map.max.0)
+            .map(|x| match grid.get(&Point { x, y }) {
+                Some((code, _)) => match Tile::from_u8(code) {
+                    Tile::Wall => GLYPH_WALL,
+                    Tile::Warp => GLYPH_WARP,
+                    Tile::NPC => GLYPH_NPC,
+                    Tile::Grass => GLYPH_GRASS,
+                    Tile::Mud => GLYPH_MUD,
+                    _ => GLYPH_EMPTY,
+                },
+                None => GLYPH_EMPTY,
+            })
+            .collect::<String>();
+        lines.push(row.trim_end().to_string());
+    }
+
+    let npc = with(Tile::NPC).collect::<Vec<_>>();
+    if !npc.is_empty() {
+        lines.push("legend".to_string());
+        for (point, meta) in npc {
+            lines.push(format!("npc {},{} {},{}", point.x, point.y, meta.0, meta.1));
+        }
+    }
+
+    lines.push("end".to_string());
+    lines.join("\n")
+}
+
+pub fn export_maps(maps: &[ParsedMap], format: Format) -> String {
+    let mut maps = maps.iter().collect::<Vec<_>>();
+    maps.sort_by_key(|map| (map.area, map.part));
+
+    let out = maps
+        .into_iter()
+        .map(|map| match format {
+            Format::Compact => to_compact(map),
+            Format::Visual => to_visual(map),
+        })
+        .collect::<Vec<_>>();
+
+    match format {
+        Format::Compact => out.join("\n") + "\n",
+        Format::Visual => out.join("\n\n") + "\n",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::parse_maps;
+
+    fn bundled() -> Vec<ParsedMap> {
+        parse_maps(include_str!("../../lib/maps")).unwrap()
+    }
+
+    #[test]
+    fn it_should_round_trip_the_bundled_maps() {
+        for format in [Format::Compact, Format::Visual].iter() {
+            let exported = export_maps(&bundled(), *format);
+            assert_eq!(parse_maps(&exported).unwrap(), bundled());
+        }
+    }
+
+    #[test]
+    fn it_should_convert_between_formats() {
+        let visual = export_maps(&bundled(), Format::Visual);
+        let compact = export_maps(&parse_maps(&visual).unwrap(), Format::Compact);
+
+        assert_eq!(compact, export_maps(&bundled(), Format::Compact));
+    }
+
+    #[test]
+    fn it_should_compress_runs_into_ranges() {
+        let map = parse_maps("0|0|4,3|1,1,0\n0-3,0|0,1|3,1|0-3,2|2,1,4,1,0").unwrap();
+
+        assert_eq!(
+            to_compact(&map[0]),
+            "0|0|4,3|1,1,0\n0-3,0|0,1-2|2,1,4,1,0|3,1-2|1-2,2"
+        );
+    }
+}
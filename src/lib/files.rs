@@ -0,0 +1,214 @@
+use crate::helper::MapError;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+///
+/// `maps` next to the binary, either a single file or a directory with one
+/// file per area.
+///
+pub fn default_maps_path() -> Option<PathBuf> {
+    let path = env::current_exe().ok()?.parent()?.join("maps");
+
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn files_in(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_owned()]);
+    }
+
+    let mut files = fs::read_dir(path)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(
+            |path| match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => !name.starts_with('.'),
+                None => false,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    files.sort();
+
+    Ok(files)
+}
+
+///
+/// Reads every file in `paths`, directories are expanded to the files they
+/// contain in name order. Returns the files read alongside their contents.
+///
+pub fn read_maps(paths: &[PathBuf]) -> io::Result<Vec<(PathBuf, String)>> {
+    let mut out = Vec::new();
+
+    for path in paths {
+        for file in files_in(path)? {
+            let content = fs::read_to_string(&file)?;
+            out.push((file, content));
+        }
+    }
+
+    Ok(out)
+}
+
+pub struct Source {
+    pub text: String,
+    files: Vec<(PathBuf, usize)>,
+    embedded: bool,
+}
+
+impl Source {
+    pub fn new(path: &Path, text: &str) -> Source {
+        Source {
+            text: text.to_string(),
+            files: vec![(path.to_owned(), 1)],
+            embedded: false,
+        }
+    }
+
+    ///
+    /// The maps built into the binary, named after the file they were built
+    /// from but with nowhere to write them back to.
+    ///
+    pub fn embedded(path: &Path, text: &str) -> Source {
+        Source {
+            embedded: true,
+            ..Source::new(path, text)
+        }
+    }
+
+    pub fn is_embedded(&self) -> bool {
+        self.embedded
+    }
+
+    ///
+    /// The file a line of the joined `text` came from, and the line in it.
+    ///
+    pub fn locate(&self, line: usize) -> (&Path, usize) {
+        let (path, first) = self
+            .files
+            .iter()
+            .rev()
+            .find(|(_, first)| *first <= line)
+            .unwrap_or(&self.files[0]);
+
+        (path, line + 1 - first)
+    }
+
+    pub fn describe(&self, error: &MapError) -> String {
+        let (path, line) = self.locate(error.line);
+        format!(
+            "{}:{}:{}: {} ({})",
+            path.display(),
+            line,
+            error.column,
+            error.kind,
+            error.field
+        )
+    }
+}
+
+///
+/// All map files in `paths` joined into one source for `helper::parse_maps`,
+/// or `None` when there was nothing to read.
+///
+pub fn maps_source(paths: &[PathBuf]) -> io::Result<Option<Source>> {
+    let mut source = Source {
+        text: String::new(),
+        files: Vec::new(),
+        embedded: false,
+    };
+    let mut line = 1;
+
+    for (path, content) in read_maps(paths)? {
+        let content = content.trim_end();
+        if content.is_empty() {
+            continue;
+        }
+
+        if !source.text.is_empty() {
+            source.text.push('\n');
+        }
+
+        source.text.push_str(content);
+        source.files.push((path, line));
+        line += content.lines().count();
+    }
+
+    if source.files.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///
+    /// An empty directory of its own for every test, under the temp dir.
+    ///
+    fn directory(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("terminal-madness-files-{}", name));
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn names(files: &[(PathBuf, String)]) -> Vec<String> {
+        files
+            .iter()
+            .map(|(path, _)| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn it_should_read_a_directory_in_name_order() {
+        let path = directory("sorted");
+        fs::write(path.join("b"), "0|1|1,1|0,0,0\n").unwrap();
+        fs::write(path.join("a"), "0|0|1,1|0,0,0\n0,0\n\n").unwrap();
+        fs::write(path.join(".hidden"), "not a map").unwrap();
+
+        let files = read_maps(&[path.to_owned()]).unwrap();
+        assert_eq!(names(&files), vec!["a", "b"]);
+
+        let source = maps_source(&[path.to_owned()]).unwrap().unwrap();
+        assert_eq!(source.text, "0|0|1,1|0,0,0\n0,0\n0|1|1,1|0,0,0");
+        assert_eq!(source.locate(1), (path.join("a").as_path(), 1));
+        assert_eq!(source.locate(3), (path.join("b").as_path(), 1));
+    }
+
+    #[test]
+    fn it_should_read_every_path_given_in_order() {
+        let path = directory("paths");
+        let maps = path.join("maps");
+        fs::create_dir(&maps).unwrap();
+        fs::write(maps.join("a"), "0|0|1,1|0,0,0").unwrap();
+        fs::write(maps.join("b"), "0|1|1,1|0,0,0").unwrap();
+        fs::write(path.join("c"), "1|0|1,1|0,0,0").unwrap();
+
+        let files = read_maps(&[path.join("c"), maps.to_owned()]).unwrap();
+        assert_eq!(names(&files), vec!["c", "a", "b"]);
+
+        assert!(read_maps(&[path.join("missing")]).is_err());
+    }
+
+    #[test]
+    fn it_should_have_no_source_when_there_is_nothing_to_read() {
+        let path = directory("empty");
+        assert!(read_maps(&[path.to_owned()]).unwrap().is_empty());
+        assert!(maps_source(&[path.to_owned()]).unwrap().is_none());
+
+        fs::write(path.join("blank"), "\n  \n").unwrap();
+        assert!(maps_source(&[path]).unwrap().is_none());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{
+    helper::{Field, MapError, MapErrorKind, Parser, Token},
+    shared::{ParsedMap, Point},
+};
+use std::collections::{HashMap, HashSet};
+
+pub type Tiles = HashMap<Point, (u8, (u8, u8))>;
+
+pub type Triggers = HashMap<String, Vec<((u8, u8), Tiles)>>;
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Flag {
+    Set(String),
+    Clear(String),
+    Requires(String),
+    Forbids(String),
+}
+
+///
+/// Comma separated, `+name` sets a flag, `-name` clears it, `?name` requires
+/// it to be set and `!name` requires it to be unset.
+///
+pub fn parse_flags(str: &str) -> Vec<Flag> {
+    str.split(',')
+        .map(str::trim)
+        .filter(|flag| flag.len() > 1)
+        .filter_map(|flag| {
+            let name = flag[1..].to_string();
+            match &flag[..1] {
+                "+" => Some(Flag::Set(name)),
+                "-" => Some(Flag::Clear(name)),
+                "?" => Some(Flag::Requires(name)),
+                "!" => Some(Flag::Forbids(name)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+pub fn holds(flags: &[Flag], set: &HashSet<String>) -> bool {
+    flags.iter().all(|flag| match flag {
+        Flag::Requires(name) => set.contains(name),
+        Flag::Forbids(name) => !set.contains(name),
+        _ => true,
+    })
+}
+
+///
+/// One trigger per line, `flag|area,part|tiles`, where `tiles` uses the same
+/// encoding as the last line of a map in `lib/maps` and has to fit the map
+/// in `maps` it is for. Setting the flag replaces those tiles; clearing it
+/// again does not put them back.
+///
+pub fn parse_triggers(str: &str, maps: &[ParsedMap]) -> Result<Triggers, MapError> {
+    let mut parser = Parser {
+        line: 0,
+        field: Field::Trigger,
+        errors: Vec::new(),
+    };
+    let mut triggers: Triggers = HashMap::new();
+
+    for (i, text) in str.lines().enumerate() {
+        let line = Token::line(text);
+        if line.text.is_empty() {
+            continue;
+        }
+        parser.line = i + 1;
+
+        let fields = line.split('|');
+        if let Some(missing) = ["flag", "map", "tiles"].get(fields.len()) {
+            parser.error(line.end(), MapErrorKind::Missing(missing));
+            continue;
+        }
+        if fields[0].text.is_empty() {
+            parser.error(fields[0].column, MapErrorKind::Missing("flag"));
+            continue;
+        }
+
+        let key = match parser.values(&fields[1], ',', &["area", "part"], 2) {
+            Some(key) => (parser.number(&key[0]), parser.number(&key[1])),
+            None => continue,
+        };
+        let key = match key {
+            (Some(area), Some(part)) => (area, part),
+            _ => continue,
+        };
+
+        let mut map = match maps.iter().find(|map| (map.area, map.part) == key) {
+            Some(map) => ParsedMap {
+                grid: HashMap::new(),
+                ..map.to_owned()
+            },
+            None => {
+                parser.error(fields[1].column, MapErrorKind::NoMap(key.0, key.1));
+                continue;
+            }
+        };
+
+        let tiles = Token {
+            text: line.text.splitn(3, '|').nth(2).unwrap_or(""),
+            column: fields[2].column,
+        };
+        for (column, target) in parser.tiles(&tiles, &mut map) {
+            if !maps.iter().any(|map| (map.area, map.part) == target) {
+                parser.error(column, MapErrorKind::WarpTarget(target.0, target.1));
+            }
+        }
+
+        let flag = fields[0].text.to_string();
+        triggers.entry(flag).or_default().push((key, map.grid));
+    }
+
+    match parser.errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(triggers),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::parse_maps;
+
+    #[test]
+    fn it_should_check_conditions_against_set_flags() {
+        let flags = parse_flags("?gate,!angry,+talked");
+        let mut set = HashSet::new();
+
+        assert!(!holds(&flags, &set));
+
+        set.insert("gate".to_string());
+        assert!(holds(&flags, &set));
+
+        set.insert("angry".to_string());
+        assert!(!holds(&flags, &set));
+    }
+
+    fn maps() -> Vec<ParsedMap> {
+        parse_maps("0|1|12,6|1,1,0\n0,0").unwrap()
+    }
+
+    fn error(str: &str) -> (usize, usize, MapErrorKind) {
+        let error = parse_triggers(str, &maps()).unwrap_err();
+        (error.line, error.column, error.kind)
+    }
+
+    #[test]
+    fn it_should_parse_triggers_per_map() {
+        let triggers = parse_triggers("gate|0,1|10,4-5,1|3,3,3,0,1", &maps()).unwrap();
+        let (map, tiles) = &triggers["gate"][0];
+
+        assert_eq!(map, &(0, 1));
+        assert_eq!(tiles.get(&Point { x: 10, y: 5 }), Some(&(1, (0, 0))));
+        assert_eq!(tiles.get(&Point { x: 3, y: 3 }), Some(&(3, (0, 1))));
+        assert_eq!(tiles.len(), 3);
+    }
+
+    #[test]
+    fn it_should_point_at_broken_triggers() {
+        assert_eq!(
+            error("\ngate|0,1|10,4-x,1"),
+            (2, 15, MapErrorKind::Number("x".to_string()))
+        );
+        assert_eq!(
+            error("gate|0,1|1,1,1|40,2,1"),
+            (1, 16, MapErrorKind::OutOfBounds(Point { x: 40, y: 2 }))
+        );
+        assert_eq!(error("gate|1,1|1,1,1"), (1, 6, MapErrorKind::NoMap(1, 1)));
+        assert_eq!(
+            error("gate|0,1|1,1,3,2,0"),
+            (1, 10, MapErrorKind::WarpTarget(2, 0))
+        );
+        assert_eq!(error("gate|0,1"), (1, 9, MapErrorKind::Missing("tiles")));
+    }
+}
@@ -1,12 +1,27 @@
-use crate::lib::{
+use crate::{
+    camera::{needed, too_small, Camera},
+    dialogue::{dialogue_box, Choice, Dialogue, Node, Speaker},
+    events::{Bus, Command, Event, Subscriber},
+    flags::{holds, Flag, Triggers},
+    hud::{describe, log_panel, status, HUD_WIDTH},
+    input::{Action, Movement},
     map::Map,
-    pathfinding::find_path,
+    messages::{history_screen, Logger, MessageLog, Severity},
+    pathfinding::{adjacent, find_path, Neighbours, Reservations},
+    render::Frame,
+    save::Save,
     shared::{rand_range, Direction, ParsedMap, Point, Tile},
+    splash::{card, title_screen, MenuItem, Screens, MENU},
 };
+use rand::{rngs::StdRng, SeedableRng};
 use std::{
-    collections::HashMap,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fs, io, mem,
     ops::RangeInclusive,
-    sync::{Arc, RwLock},
+    path::PathBuf,
+    rc::Rc,
+    time::Duration,
 };
 
 #[derive(Clone)]
@@ -19,20 +34,66 @@ struct MapMeta {
 
 type Range = (RangeInclusive<u8>, RangeInclusive<u8>);
 
-#[allow(dead_code)]
-#[derive(Clone)]
+type Paths = HashMap<(u8, u8, u8), Vec<Point>>;
+
+///
+/// How long one step of the game takes, everything that happens on its own
+/// happens on a whole number of them.
+///
+pub const TICK: Duration = Duration::from_millis(50);
+
+///
+/// How many ticks pass between npc steps.
+///
+const NPC_TICKS: u64 = 20;
+
+///
+/// How far along its route a blocked npc tries to get back onto it, and how
+/// many steps longer than the blocked stretch the way around may be.
+///
+const REJOIN: usize = 4;
+const DETOUR: usize = 4;
+
+///
+/// How many steps a blocked npc waits for the way to clear before stepping
+/// aside.
+///
+const PATIENCE: usize = 2;
+
 pub struct Game {
     current_map: (u8, u8),
-    dialogue: Option<u8>,
+    dialogue: Option<(u8, u8, usize)>,
+    dialogues: HashMap<Speaker, Dialogue>,
     entries: HashMap<(u8, u8), Point>,
+    history: Option<usize>,
+    log: Rc<RefCell<MessageLog>>,
+    movement: Movement,
+    neighbours: Neighbours,
+    waiting: HashMap<u8, usize>,
+    flags: HashSet<String>,
     pub map: Map,
     maps: HashMap<(u8, u8), MapMeta>,
+    menu: Option<usize>,
+    screens: Screens,
     splash: Option<u8>,
-    pub pathfinding: Arc<RwLock<HashMap<(u8, u8, u8), Vec<Point>>>>,
+    triggers: Triggers,
+    can_continue: bool,
+    save_path: Option<PathBuf>,
+    changed: bool,
+    pathfinding: Paths,
+    running: bool,
+    rng: StdRng,
+    ticks: u64,
+    bus: Bus,
 }
 
 impl Game {
-    pub fn new(input: Vec<ParsedMap>) -> Game {
+    pub fn new(
+        input: Vec<ParsedMap>,
+        dialogues: HashMap<Speaker, Dialogue>,
+        triggers: Triggers,
+        screens: Screens,
+    ) -> Game {
         let mut maps = HashMap::new();
         for parsed in input.into_iter() {
             maps.insert(
@@ -63,107 +124,356 @@ impl Game {
         let mut game = Game {
             current_map,
             dialogue: None,
+            dialogues,
             entries: HashMap::new(),
+            history: None,
+            log: Rc::new(RefCell::new(MessageLog::default())),
+            movement: Movement::default(),
+            neighbours: Neighbours::default(),
+            waiting: HashMap::new(),
+            flags: HashSet::new(),
             map: Map::parse_map(&map.grid, &map.max, &map.player, &map.props),
             maps,
+            menu: Some(0),
+            screens,
             splash: None,
-            pathfinding: Arc::new(RwLock::new(HashMap::new())),
+            triggers,
+            can_continue: false,
+            save_path: None,
+            changed: false,
+            pathfinding: HashMap::new(),
+            running: true,
+            rng: StdRng::seed_from_u64(0),
+            ticks: 0,
+            bus: Bus::default(),
         };
 
+        let log = game.log.clone();
+        game.subscribe(Box::new(Logger::new(log)));
         game.new_path_for_npc();
 
         game
     }
 
-    fn gen_point(&self, range: &Range, iteration: usize) -> Point {
+    ///
+    /// A random point in `range` to walk to from `from`, points that are
+    /// taken or can't be reached from there are passed over.
+    ///
+    fn gen_point(&mut self, range: &Range, from: &Point, iteration: usize) -> Option<Point> {
+        if iteration > 10 {
+            return None;
+        }
+
         let point = Point {
-            x: rand_range(&range.0) as usize,
-            y: rand_range(&range.1) as usize,
+            x: rand_range(&mut self.rng, &range.0) as usize,
+            y: rand_range(&mut self.rng, &range.1) as usize,
         };
 
-        if self.map.grid.get(&point) == Some(&Tile::Empty) || iteration > 10 {
-            return point;
+        let walkable = self.map.grid.get(&point).is_some_and(Tile::is_floor);
+        if walkable && self.map.navigation.reachable(from, &point) {
+            return Some(point);
         }
 
-        self.gen_point(range, iteration + 1)
+        self.gen_point(range, from, iteration + 1)
     }
 
-    pub fn new_path_for_npc(&mut self) {
-        let mut pathfinding = self.pathfinding.write().unwrap();
-        for npc in &self.map.npc {
-            let key = (npc.0.to_owned(), self.current_map.0, self.current_map.1);
-            if let Some(val) = pathfinding.get(&key) {
-                if !val.is_empty() {
-                    continue;
-                }
+    ///
+    /// Where the player and every npc but those in `skip` will be, for a
+    /// route to be planned around.
+    ///
+    fn reservations(&self, paths: &Paths, skip: &[u8]) -> Reservations {
+        let mut reservations = Reservations::default();
+        reservations.hold(self.map.current);
+
+        for (id, point) in &self.map.npc {
+            if skip.contains(id) {
+                continue;
             }
 
-            if let Some(meta) = self.map.props.get(npc.0) {
-                let point = self.gen_point(&meta, 0);
-                let path = find_path(&self.map.grid, npc.1.to_owned(), point.to_owned());
+            let key = (id.to_owned(), self.current_map.0, self.current_map.1);
+            match paths.get(&key) {
+                Some(path) => reservations.reserve(point.to_owned(), path),
+                None => reservations.hold(point.to_owned()),
+            }
+        }
 
-                if path.is_empty() {
-                    continue;
-                }
+        reservations
+    }
+
+    ///
+    /// Gives every npc that has nowhere to go a new route, in order of id,
+    /// each planned around the routes of those before it.
+    ///
+    pub fn new_path_for_npc(&mut self) {
+        let (area, part) = self.current_map;
+
+        let mut pending = self
+            .map
+            .npc
+            .keys()
+            .filter(|id| {
+                self.pathfinding
+                    .get(&(**id, area, part))
+                    .is_none_or(Vec::is_empty)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        pending.sort();
+
+        while !pending.is_empty() {
+            let id = pending.remove(0);
+            let range = match self.map.props.get(&id) {
+                Some(range) => range.to_owned(),
+                None => continue,
+            };
+
+            let start = self.map.npc[&id];
+            let goal = match self.gen_point(&range, &start, 0) {
+                Some(goal) => goal,
+                None => continue,
+            };
+            let mut skip = pending.to_owned();
+            skip.push(id);
 
-                pathfinding.insert(key, path.to_owned());
+            let path = find_path(
+                &self.map.navigation,
+                start,
+                goal,
+                self.neighbours,
+                &self.reservations(&self.pathfinding, &skip),
+            );
+
+            if path.is_empty() {
+                continue;
             }
+
+            self.pathfinding.insert((id, area, part), path);
         }
     }
 
-    pub fn move_player(&mut self, input: &u8) {
-        let mut direction = self.map.direction.to_owned();
-        let current = self.map.current.to_owned();
-        let mut point = current.clone();
-        match input {
-            65 => {
-                if direction == Direction::Up && point.y > 0 {
-                    point.y -= 1;
-                }
-                direction = Direction::Up;
-            }
-            66 => {
-                if direction == Direction::Down {
-                    point.y += 1;
-                }
-                direction = Direction::Down;
-            }
-            67 => {
-                if direction == Direction::Right {
-                    point.x += 1;
-                }
-                direction = Direction::Right;
-            }
-            68 => {
-                if direction == Direction::Left && point.x > 0 {
-                    point.x -= 1;
+    ///
+    /// Starts the npc over with routes from `seed`, so that the same seed and
+    /// the same input make the same game.
+    ///
+    pub fn set_seed(&mut self, seed: u64) {
+        self.log(Severity::Info, format!("seed {}", seed));
+        self.rng = StdRng::seed_from_u64(seed);
+        self.pathfinding.clear();
+        self.new_path_for_npc();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    ///
+    /// Has `subscriber` hear about every event that happens from now on.
+    ///
+    pub fn subscribe(&mut self, subscriber: Box<dyn Subscriber>) {
+        self.bus.subscribe(subscriber);
+    }
+
+    fn emit(&mut self, event: Event) {
+        self.bus.emit(event);
+    }
+
+    ///
+    /// Moves the game on by one `TICK`, returning what happened since the
+    /// last one.
+    ///
+    pub fn tick(&mut self) -> Vec<Event> {
+        self.ticks += 1;
+
+        if self.ticks.is_multiple_of(NPC_TICKS) {
+            self.step_npcs();
+        }
+
+        self.publish()
+    }
+
+    ///
+    /// Hands every subscriber what happened since the last time, ending on
+    /// `Changed` if anything on screen did, and returns it.
+    ///
+    pub fn publish(&mut self) -> Vec<Event> {
+        if mem::take(&mut self.changed) {
+            self.emit(Event::Changed);
+        }
+
+        let mut bus = mem::take(&mut self.bus);
+        let events = bus.publish(self);
+        self.bus = bus;
+        events
+    }
+
+    ///
+    /// Acts on `action` and moves the game on by one tick, as if it was
+    /// pressed between two.
+    ///
+    pub fn step(&mut self, action: &Action) -> Vec<Event> {
+        self.act(action);
+        self.tick()
+    }
+
+    ///
+    /// Lets `ticks` ticks pass without any input.
+    ///
+    pub fn wait(&mut self, ticks: u64) -> Vec<Event> {
+        (0..ticks).flat_map(|_| self.tick()).collect()
+    }
+
+    ///
+    /// Moves every npc one step along its route, in order of id.
+    ///
+    fn step_npcs(&mut self) {
+        let mut steps = self
+            .pathfinding
+            .iter_mut()
+            .filter_map(|(meta, path)| Some((meta.to_owned(), path.pop()?)))
+            .collect::<Vec<_>>();
+        steps.sort();
+
+        for (meta, point) in steps {
+            self.move_npc(&meta, &point);
+        }
+    }
+
+    pub fn handle(&mut self, command: &Command) {
+        match command {
+            Command::Act(action) => self.act(action),
+            Command::Resize => self.redraw(),
+        }
+    }
+
+    pub fn act(&mut self, action: &Action) {
+        match action {
+            Action::Move(direction) => self.move_player(direction),
+            Action::Turn(direction) => self.turn(direction),
+            Action::Interact => self.interact(),
+            Action::Confirm => self.confirm(),
+            Action::Choose(index) => self.choose(*index),
+            Action::Save => self.save_game(),
+            Action::History => self.toggle_history(),
+            Action::Quit => self.running = false,
+        }
+    }
+
+    pub fn set_movement(&mut self, movement: Movement) {
+        self.movement = movement;
+    }
+
+    pub fn set_neighbours(&mut self, neighbours: Neighbours) {
+        self.neighbours = neighbours;
+    }
+
+    ///
+    /// Faces `direction` without stepping, anywhere but on the map it is the
+    /// same as moving.
+    ///
+    pub fn turn(&mut self, direction: &Direction) {
+        let open = self.menu.is_some() || self.splash.is_some() || self.history.is_some();
+        if open || self.dialogue.is_some() {
+            self.move_player(direction);
+            return;
+        }
+
+        self.map.direction = direction.to_owned();
+        self.changed = true;
+    }
+
+    pub fn move_player(&mut self, input: &Direction) {
+        if let Some(selected) = self.menu {
+            let step = match input {
+                Direction::Up => MENU.len() - 1,
+                Direction::Down => 1,
+                _ => 0,
+            };
+            let mut selected = selected;
+            for _ in 0..MENU.len() {
+                selected = (selected + step) % MENU.len();
+                if self.menu_enabled(&MENU[selected]) {
+                    break;
                 }
-                direction = Direction::Left;
             }
-            _ => {}
+            self.menu = Some(selected);
+            self.changed = true;
+            return;
+        }
+
+        if self.splash.is_some() {
+            return;
+        }
+
+        if let Some(offset) = self.history {
+            let last = self.log.borrow().count().saturating_sub(1);
+            self.history = Some(match input {
+                Direction::Up => (offset + 1).min(last),
+                Direction::Down => offset.saturating_sub(1),
+                _ => offset,
+            });
+            self.changed = true;
+            return;
+        }
+
+        if let Some((id, node, selected)) = self.dialogue {
+            let choices = self
+                .current_node()
+                .map(|node| self.visible_choices(node).len())
+                .unwrap_or(0);
+            let selected = match input {
+                Direction::Up => selected.saturating_sub(1),
+                Direction::Down => (selected + 1).min(choices.saturating_sub(1)),
+                _ => selected,
+            };
+            self.dialogue = Some((id, node, selected));
+            self.changed = true;
+            return;
+        }
+
+        let current = self.map.current.to_owned();
+        let mut point = current;
+        let step = match self.movement {
+            Movement::Classic => &self.map.direction == input,
+            Movement::Step => true,
         };
 
-        if self.map.direction != direction {
-            self.map.direction = direction;
+        if step {
+            match input {
+                Direction::Up => point.y = point.y.saturating_sub(1),
+                Direction::Down => point.y += 1,
+                Direction::Right => point.x += 1,
+                Direction::Left => point.x = point.x.saturating_sub(1),
+            };
         }
 
+        self.map.direction = input.to_owned();
+
         if point == current {
-            self.map.print_grid();
+            self.changed = true;
             return;
         }
 
+        let ground = self.map.ground(&current);
         if let Some(tile) = self.map.grid.get_mut(&point) {
             match tile {
-                Tile::Empty => {
+                Tile::Empty | Tile::Grass | Tile::Mud => {
                     *tile = Tile::Current;
 
-                    *self.map.grid.get_mut(&current).unwrap() = Tile::Empty;
+                    *self.map.grid.get_mut(&current).unwrap() = ground;
                     self.map.current = point.to_owned();
+                    self.emit(Event::PlayerMoved {
+                        from: current,
+                        to: point,
+                    });
                 }
                 Tile::Warp => {
                     if let Some(meta) = self.map.meta.get(&point) {
-                        if let Some(new_map_meta) = self.maps.get(&meta) {
-                            let player = match self.entries.get(&meta) {
+                        if let Some(new_map_meta) = self.maps.get(meta) {
+                            let player = match self.entries.get(meta) {
                                 Some(point) => (point.x, point.y, self.map.direction.to_u8()),
                                 None => new_map_meta.player,
                             };
@@ -174,84 +484,724 @@ impl Game {
                                 &new_map_meta.props,
                             );
 
-                            let mut pathfinding = self.pathfinding.write().unwrap();
-                            pathfinding.clear();
-                            drop(pathfinding);
+                            self.pathfinding.clear();
+                            self.waiting.clear();
+
+                            let from = self.current_map;
+                            let area = from.0;
 
                             self.entries.insert(self.current_map, self.map.current);
                             self.current_map = meta.to_owned();
                             self.map = map;
+                            self.emit(Event::Warped {
+                                from,
+                                to: self.current_map,
+                            });
                             self.new_path_for_npc();
+
+                            if self.current_map.0 != area {
+                                self.show_splash(self.current_map.0);
+                            }
+
+                            let name = self.screens.name(self.current_map);
+                            self.log(Severity::Info, format!("you enter {}", name));
+                            if let Err(error) = self.save() {
+                                let message = format!("could not save: {}", error);
+                                self.log(Severity::Error, message);
+                            }
                         }
                     }
                 }
-                _ => {}
+                _ => self.emit(Event::Blocked { at: point }),
             }
         }
-        self.map.print_grid();
+        self.changed = true;
     }
 
-    pub fn interact(&mut self) {
-        let mut looking_at = self.map.current.to_owned();
-
-        match self.map.direction {
-            Direction::Down => looking_at.y += 1,
-            Direction::Left => looking_at.x -= 1,
-            Direction::Right => looking_at.x += 1,
-            Direction::Up => looking_at.y -= 1,
+    ///
+    /// What there is to see on a screen of `screen` columns and rows.
+    ///
+    pub fn lines(&self, screen: (usize, usize)) -> Vec<String> {
+        let screen_lines = match (self.menu, self.splash) {
+            (Some(selected), _) => Some(title_screen(
+                &self.screens.title,
+                selected,
+                self.can_continue,
+            )),
+            (_, Some(area)) => self.screens.cards.get(&area).map(|lines| card(lines)),
+            _ => None,
         };
 
-        if let Some(tile) = self.map.grid.get(&looking_at) {
-            print!("you stand next to \"{:?}\"\r\n", tile);
+        if let (None, Some(offset)) = (&screen_lines, self.history) {
+            return history_screen(&self.log.borrow(), offset, screen);
+        }
+
+        if let Some(lines) = screen_lines {
+            let need = Frame::new(&lines).size();
+            return too_small(screen, need).unwrap_or(lines);
+        }
+
+        let width = self.map.size().0.max(HUD_WIDTH).min(screen.0);
+        let facing = self.looking_at();
+        let mut below = status(
+            &self.screens.name(self.current_map),
+            self.current_map,
+            &self.map.current,
+            &self.map.direction,
+            facing.as_ref().and_then(|point| self.map.grid.get(point)),
+            width,
+        );
+
+        below.extend(match (self.current_node(), self.dialogue) {
+            (Some(node), Some((_, _, selected))) => {
+                let choices = self
+                    .visible_choices(node)
+                    .iter()
+                    .map(|choice| choice.text.as_str())
+                    .collect::<Vec<_>>();
+
+                dialogue_box(&node.text, &choices, selected, width)
+            }
+            _ => log_panel(&self.log.borrow(), width),
+        });
+
+        let need = needed(self.map.size(), Frame::new(&below).size());
+        if let Some(notice) = too_small(screen, need) {
+            return notice;
         }
+
+        let view = (screen.0, screen.1 - below.len());
+        let camera = Camera::follow(self.map.size(), &self.map.current, view);
+        self.map.grid_lines(&camera, &below)
     }
 
-    pub fn move_npc(&mut self, meta: &(u8, u8, u8), point: &Point) {
-        if self.current_map != (meta.1, meta.2) {
+    fn log(&mut self, severity: Severity, message: String) {
+        self.emit(Event::Said {
+            ticks: self.ticks,
+            severity,
+            text: message,
+        });
+    }
+
+    ///
+    /// Opens the full screen message history, or closes it again.
+    ///
+    pub fn toggle_history(&mut self) {
+        if self.menu.is_some() || self.splash.is_some() {
             return;
         }
 
-        if let Some(npc) = self.map.npc.get_mut(&meta.0) {
-            let is_not_occupied = self.map.grid.get(point).unwrap() == &Tile::Empty;
+        self.history = match self.history {
+            Some(_) => None,
+            None => Some(0),
+        };
+        self.changed = true;
+    }
 
-            let key = meta.to_owned();
+    ///
+    /// Saves and says how that went in the log.
+    ///
+    pub fn save_game(&mut self) {
+        let (severity, message) = match (self.save(), &self.save_path) {
+            (Ok(()), Some(_)) => (Severity::Info, "game saved".to_string()),
+            (Ok(()), None) => (Severity::Warning, "there is nowhere to save to".to_string()),
+            (Err(error), _) => (Severity::Error, format!("could not save: {}", error)),
+        };
+        self.log(severity, message);
+        self.changed = true;
+    }
 
-            if is_not_occupied {
-                let tile = self.map.grid.get_mut(npc).unwrap();
-                *tile = Tile::Empty;
-                let tile = self.map.grid.get_mut(point).unwrap();
-                *tile = Tile::NPC;
-                *npc = point.to_owned();
-                self.map.print_grid();
+    ///
+    /// Draws everything again, for when the terminal changed under us.
+    ///
+    pub fn redraw(&mut self) {
+        self.emit(Event::Resized);
+        self.changed = true;
+    }
 
-                let mut calculate_new = false;
+    fn menu_enabled(&self, item: &MenuItem) -> bool {
+        item != &MenuItem::Continue || self.can_continue
+    }
 
-                if let Ok(pathfinding) = self.pathfinding.read() {
-                    if let Some(npc) = pathfinding.get(&key) {
-                        if npc.len() == 0 {
-                            calculate_new = true;
-                        }
-                    }
+    fn show_splash(&mut self, area: u8) {
+        if self.screens.cards.contains_key(&area) {
+            self.splash = Some(area);
+        }
+    }
+
+    fn select_menu(&mut self, item: MenuItem) {
+        match item {
+            MenuItem::NewGame => {
+                self.menu = None;
+                self.show_splash(self.current_map.0);
+            }
+            MenuItem::Continue => {
+                if let Err(error) = self.load() {
+                    self.log(Severity::Error, format!("could not load: {}", error));
+                    self.can_continue = false;
+                    self.menu = Some(0);
+                } else {
+                    self.menu = None;
                 }
+            }
+            MenuItem::Quit => self.running = false,
+        }
+        self.changed = true;
+    }
+
+    pub fn set_save_path(&mut self, path: PathBuf) {
+        self.can_continue = path.is_file();
+        self.save_path = Some(path);
+    }
+
+    pub fn to_save(&self) -> Save {
+        Save {
+            map: self.current_map,
+            player: (
+                self.map.current.x,
+                self.map.current.y,
+                self.map.direction.to_u8(),
+            ),
+            entries: self.entries.iter().map(|(k, v)| (*k, *v)).collect(),
+            npc: self.map.npc.iter().map(|(k, v)| (*k, *v)).collect(),
+            flags: self.flags.iter().cloned().collect(),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        match &self.save_path {
+            Some(path) => fs::write(path, self.to_save().to_string()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn load(&mut self) -> io::Result<()> {
+        let path = match &self.save_path {
+            Some(path) => path,
+            None => return Err(io::ErrorKind::NotFound.into()),
+        };
+
+        let save = Save::parse(&fs::read_to_string(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+
+        self.apply_save(&save);
+
+        Ok(())
+    }
+
+    pub fn apply_save(&mut self, save: &Save) {
+        for flag in &save.flags {
+            self.set_flag(flag);
+        }
+
+        let meta = match self.maps.get(&save.map) {
+            Some(meta) => meta,
+            None => return,
+        };
+
+        let mut map = Map::parse_map(&meta.grid, &meta.max, &save.player, &meta.props);
+
+        // lift every npc off the grid first, so that they can swap places
+        for npc in map.npc.values() {
+            let ground = map.ground(npc);
+            map.grid.insert(*npc, ground);
+        }
+
+        let mut placed = map
+            .npc
+            .iter()
+            .map(|(id, npc)| match save.npc.get(id) {
+                Some(point) if map.grid.get(point).is_some_and(Tile::is_floor) => (*id, *point),
+                _ => (*id, *npc),
+            })
+            .collect::<HashMap<_, _>>();
+
+        // two on the same cell means the save is off, keep them where they start
+        if placed.values().collect::<HashSet<_>>().len() != placed.len() {
+            placed = map.npc.to_owned();
+        }
+
+        for point in placed.values() {
+            map.grid.insert(*point, Tile::NPC);
+        }
+        map.npc = placed;
+
+        self.pathfinding.clear();
+        self.waiting.clear();
+
+        self.current_map = save.map;
+        self.entries = save.entries.iter().map(|(k, v)| (*k, *v)).collect();
+        self.map = map;
+        self.dialogue = None;
+        self.splash = None;
+        self.new_path_for_npc();
+    }
+
+    fn current_node(&self) -> Option<&Node> {
+        let (id, node, _) = self.dialogue?;
+        let speaker = (id, self.current_map.0, self.current_map.1);
+
+        self.dialogues.get(&speaker)?.get(&node)
+    }
+
+    fn visible_choices<'a>(&self, node: &'a Node) -> Vec<&'a Choice> {
+        node.choices
+            .iter()
+            .filter(|choice| holds(&choice.flags, &self.flags))
+            .collect()
+    }
+
+    fn open_dialogue(&mut self, id: u8, candidates: &[u8]) {
+        let speaker = (id, self.current_map.0, self.current_map.1);
+        let mut candidates = candidates.to_vec();
+
+        self.dialogue = None;
+
+        // text-less nodes only route to their `next`, bail out if they loop
+        for _ in 0..u8::MAX {
+            let found = self.dialogues.get(&speaker).and_then(|dialogue| {
+                candidates
+                    .iter()
+                    .filter_map(|index| dialogue.get(index).map(|node| (index, node)))
+                    .find(|(_, node)| holds(&node.flags, &self.flags))
+                    .map(|(index, node)| (index.to_owned(), node.to_owned()))
+            });
+
+            let (index, node) = match found {
+                Some(found) => found,
+                None => return,
+            };
+
+            self.apply_flags(&node.flags);
+
+            if node.text.is_empty() && node.choices.is_empty() {
+                candidates = node.next;
+                continue;
+            }
 
-                if calculate_new {
-                    self.new_path_for_npc();
+            self.dialogue = Some((id, index, 0));
+            return;
+        }
+    }
+
+    fn apply_flags(&mut self, flags: &[Flag]) {
+        for flag in flags {
+            match flag {
+                Flag::Set(name) => self.set_flag(name),
+                Flag::Clear(name) => {
+                    self.flags.remove(name);
                 }
+                _ => {}
+            }
+        }
+    }
 
+    pub fn set_flag(&mut self, name: &str) {
+        if !self.flags.insert(name.to_string()) {
+            return;
+        }
+
+        for (key, tiles) in self.triggers.get(name).cloned().unwrap_or_default() {
+            if let Some(meta) = self.maps.get_mut(&key) {
+                meta.grid.extend(tiles.to_owned());
+            }
+
+            if key == self.current_map {
+                self.map.patch(&tiles);
+            }
+        }
+    }
+
+    fn looking_at(&self) -> Option<Point> {
+        let mut point = self.map.current.to_owned();
+
+        match self.map.direction {
+            Direction::Down => point.y += 1,
+            Direction::Left => point.x = point.x.checked_sub(1)?,
+            Direction::Right => point.x += 1,
+            Direction::Up => point.y = point.y.checked_sub(1)?,
+        };
+
+        Some(point)
+    }
+
+    fn npc_at(&self, point: &Point) -> Option<u8> {
+        if let Some((id, _)) = self.map.npc.iter().find(|(_, npc)| *npc == point) {
+            return Some(id.to_owned());
+        }
+
+        match self.map.grid.get(point) {
+            Some(Tile::NPC) => self.map.meta.get(point).map(|meta| meta.0),
+            _ => None,
+        }
+    }
+
+    pub fn interact(&mut self) {
+        if self.history.is_some() {
+            self.toggle_history();
+            return;
+        }
+
+        if self.splash.is_some() {
+            self.splash = None;
+            self.changed = true;
+            return;
+        }
+
+        if self.menu.is_some() || self.dialogue.is_some() {
+            return;
+        }
+
+        let looking_at = match self.looking_at() {
+            Some(point) => point,
+            None => return,
+        };
+
+        let npc = self.npc_at(&looking_at);
+        self.emit(Event::Interacted {
+            at: looking_at,
+            npc,
+        });
+
+        if let Some(id) = npc {
+            let speaker = (id, self.current_map.0, self.current_map.1);
+            if let Some(start) = self.dialogues.get(&speaker).map(|d| d.start) {
+                self.open_dialogue(id, &[start]);
+                self.changed = true;
                 return;
             }
+        }
 
-            drop(npc);
-            drop(is_not_occupied);
+        let message = format!(
+            "you stand next to {}",
+            describe(self.map.grid.get(&looking_at))
+        );
+        self.log(Severity::Info, message);
+        self.changed = true;
+    }
 
-            if let Ok(guard) = self.pathfinding.write() {
-                let mut pathfinding = guard;
-                if let Some(npc) = pathfinding.get_mut(&key) {
-                    npc.clear()
-                }
+    pub fn confirm(&mut self) {
+        if let Some(selected) = self.menu {
+            self.select_menu(MENU[selected]);
+            return;
+        }
+
+        if self.splash.is_some() {
+            self.splash = None;
+            self.changed = true;
+            return;
+        }
+
+        if self.history.is_some() {
+            self.toggle_history();
+            return;
+        }
+
+        let (id, selected) = match self.dialogue {
+            Some((id, _, selected)) => (id, selected),
+            None => return,
+        };
+
+        let node = match self.current_node() {
+            Some(node) => node.to_owned(),
+            None => {
+                self.dialogue = None;
+                self.changed = true;
+                return;
             }
+        };
+
+        if self.visible_choices(&node).is_empty() {
+            self.open_dialogue(id, &node.next);
+            self.changed = true;
+        } else {
+            self.choose(selected);
+        }
+    }
+
+    pub fn choose(&mut self, index: usize) {
+        let id = match self.dialogue {
+            Some((id, _, _)) => id,
+            None => return,
+        };
+
+        let choice = match self.current_node() {
+            Some(node) => self.visible_choices(node).get(index).cloned().cloned(),
+            None => None,
+        };
+
+        if let Some(choice) = choice {
+            self.apply_flags(&choice.flags);
+            self.open_dialogue(id, &[choice.target]);
+            self.changed = true;
+        }
+    }
 
+    pub fn move_npc(&mut self, meta: &(u8, u8, u8), point: &Point) {
+        if self.current_map != (meta.1, meta.2) {
+            return;
+        }
+
+        let npc = match self.map.npc.get(&meta.0) {
+            Some(npc) => npc.to_owned(),
+            None => return,
+        };
+
+        // the route waits here for someone to pass
+        if &npc == point {
+            return;
+        }
+
+        if !self.map.grid.get(point).is_some_and(Tile::is_floor) {
+            self.route_around(meta, &npc, point);
+            return;
+        }
+
+        self.waiting.remove(&meta.0);
+        *self.map.grid.get_mut(&npc).unwrap() = self.map.ground(&npc);
+        *self.map.grid.get_mut(point).unwrap() = Tile::NPC;
+        self.map.npc.insert(meta.0, point.to_owned());
+        self.emit(Event::NpcMoved {
+            id: meta.0,
+            from: npc,
+            to: point.to_owned(),
+        });
+        self.changed = true;
+
+        if self.pathfinding.get(meta).is_some_and(Vec::is_empty) {
             self.new_path_for_npc();
         }
     }
+
+    ///
+    /// Someone stands on `point`, the next step of the npc on `npc`. Rather
+    /// than planning a new route it goes around them and back onto the
+    /// route a few steps on, or waits for them to move. Once it has waited
+    /// long enough it steps aside, unless whoever is in the way is an npc
+    /// that is meant to step aside for it, and then goes somewhere else.
+    ///
+    fn route_around(&mut self, meta: &(u8, u8, u8), npc: &Point, point: &Point) {
+        let mut path = self.pathfinding.remove(meta).unwrap_or_default();
+        path.push(point.to_owned());
+
+        let mut reservations = self.reservations(&self.pathfinding, &[meta.0]);
+        reservations.hold(point.to_owned());
+
+        let rejoin = path.len().saturating_sub(REJOIN + 1);
+        let detour = find_path(
+            &self.map.navigation,
+            npc.to_owned(),
+            path[rejoin],
+            self.neighbours,
+            &reservations,
+        );
+
+        if !detour.is_empty() && detour.len() <= path.len() - rejoin + DETOUR {
+            path.truncate(rejoin);
+            path.extend(detour);
+            self.pathfinding.insert(meta.to_owned(), path);
+            self.waiting.remove(&meta.0);
+            return;
+        }
+
+        let waited = self.waiting.entry(meta.0).or_insert(0);
+        *waited += 1;
+
+        // npcs give way to those with a lower id, anything else never moves
+        let yields = match self.map.npc.iter().find(|(_, at)| at == &point) {
+            Some((id, _)) => id < &meta.0,
+            None => true,
+        };
+
+        if *waited <= PATIENCE || (!yields && *waited <= PATIENCE * 3) {
+            self.pathfinding.insert(meta.to_owned(), path);
+            return;
+        }
+
+        self.waiting.remove(&meta.0);
+        self.pathfinding.insert(meta.to_owned(), Vec::new());
+
+        let aside = adjacent(&self.map.navigation, npc)
+            .into_iter()
+            .filter(|next| self.map.grid[next].is_floor() && reservations.is_free(next, 1))
+            .min_by_key(|next| (!reservations.is_free(next, 2), path.contains(next)));
+
+        match aside {
+            Some(aside) => self.move_npc(meta, &aside),
+            None => self.new_path_for_npc(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        helper::parse_maps,
+        render::{Buffer, Screen},
+    };
+
+    ///
+    /// A game on `input` that draws into memory rather than the terminal.
+    ///
+    fn quiet(input: &str) -> Game {
+        let maps = parse_maps(input).unwrap();
+        let mut game = Game::new(maps, HashMap::new(), HashMap::new(), Screens::default());
+        game.subscribe(Box::new(Screen::new(Buffer::default())));
+        game
+    }
+
+    fn game(movement: Movement) -> Game {
+        let mut game = quiet(include_str!("../../lib/maps"));
+        game.set_movement(movement);
+        game.act(&Action::Confirm);
+        game
+    }
+
+    #[test]
+    fn it_should_turn_before_stepping_in_classic_mode() {
+        let mut game = game(Movement::Classic);
+        game.act(&Action::Move(Direction::Up));
+        assert_eq!(game.map.current, Point { x: 2, y: 7 });
+        assert_eq!(game.map.direction, Direction::Up);
+
+        game.act(&Action::Move(Direction::Up));
+        assert_eq!(game.map.current, Point { x: 2, y: 6 });
+    }
+
+    #[test]
+    fn it_should_step_at_once_or_only_turn_when_asked() {
+        let mut game = game(Movement::Step);
+        game.act(&Action::Move(Direction::Up));
+        assert_eq!(game.map.current, Point { x: 2, y: 6 });
+
+        game.act(&Action::Turn(Direction::Left));
+        assert_eq!(game.map.current, Point { x: 2, y: 6 });
+        assert_eq!(game.map.direction, Direction::Left);
+    }
+
+    #[test]
+    fn it_should_let_npc_pass_each_other_in_a_corridor() {
+        let corridor = "0|0|10,3|0,0,1\n0,8,1|1,1,1\n1-4,0|6-9,0|0-9,2|2,1,4,0,0|7,1,4,1,0";
+        let mut game = quiet(corridor);
+
+        // routes planned without each other, so they meet halfway
+        for (id, goal) in &[(0, Point { x: 8, y: 1 }), (1, Point { x: 1, y: 1 })] {
+            let start = game.map.npc[id];
+            let path = find_path(
+                &game.map.navigation,
+                start,
+                *goal,
+                Neighbours::Four,
+                &Reservations::default(),
+            );
+            game.pathfinding.insert((*id, 0, 0), path);
+        }
+
+        for _ in 0..30 {
+            game.step_npcs();
+        }
+
+        assert_eq!(game.map.npc[&0], Point { x: 8, y: 1 });
+        assert_eq!(game.map.npc[&1], Point { x: 1, y: 1 });
+    }
+
+    #[test]
+    fn it_should_walk_npcs_past_a_warp_without_waiting() {
+        let mut game = quiet("0|0|5,2|0,1,0\n0,1-4,0-1\n1,0,4,0,0|2,0,3,0,0|4,0");
+        let goal = Point { x: 3, y: 0 };
+        let path = find_path(
+            &game.map.navigation,
+            game.map.npc[&0],
+            goal,
+            Neighbours::Four,
+            &Reservations::default(),
+        );
+        assert!(!path.contains(&Point { x: 2, y: 0 }));
+        game.pathfinding.insert((0, 0, 0), path);
+
+        for _ in 0..4 {
+            game.step_npcs();
+            assert!(game.waiting.is_empty());
+        }
+        assert_eq!(game.map.npc[&0], goal);
+    }
+
+    #[test]
+    fn it_should_only_pick_goals_that_can_be_reached() {
+        let mut game = quiet("0|0|6,1|0,0,0\n0,2-5,0\n3,0|1,0,4,0,0");
+        let npc = game.map.npc[&0];
+
+        assert_eq!(
+            game.gen_point(&(2..=2, 0..=0), &npc, 0),
+            Some(Point { x: 2, y: 0 })
+        );
+        assert_eq!(game.gen_point(&(4..=5, 0..=0), &npc, 0), None);
+    }
+
+    #[test]
+    fn it_should_restore_npcs_that_swapped_places() {
+        let mut game = quiet("0|0|6,1|0,0,0\n0,1-5,0|1,1-5,0\n1,0,4,0,0|4,0,4,1,0");
+        let mut save = game.to_save();
+        save.npc.insert(0, Point { x: 4, y: 0 });
+        save.npc.insert(1, Point { x: 1, y: 0 });
+        game.apply_save(&save);
+
+        assert_eq!(game.map.npc[&0], Point { x: 4, y: 0 });
+        assert_eq!(game.map.npc[&1], Point { x: 1, y: 0 });
+        assert_eq!(game.map.grid[&Point { x: 1, y: 0 }], Tile::NPC);
+        assert_eq!(game.map.grid[&Point { x: 4, y: 0 }], Tile::NPC);
+
+        save.npc.insert(1, Point { x: 4, y: 0 });
+        game.apply_save(&save);
+        assert_eq!(game.map.npc[&0], Point { x: 1, y: 0 });
+        assert_eq!(game.map.npc[&1], Point { x: 4, y: 0 });
+    }
+
+    #[test]
+    fn it_should_log_and_draw_once_published() {
+        let buffer = Buffer::default();
+        let mut game = game(Movement::Step);
+        game.subscribe(Box::new(Screen::new(buffer.clone())));
+        let shows = |text: &str| {
+            buffer
+                .frame()
+                .plain()
+                .iter()
+                .any(|line| line.contains(text))
+        };
+
+        game.act(&Action::Save);
+        assert_eq!(game.log.borrow().count(), 0);
+        assert!(!shows("there is nowhere to save to"));
+
+        game.tick();
+        assert_eq!(game.log.borrow().count(), 1);
+        assert!(shows("there is nowhere to save to"));
+    }
+
+    #[test]
+    fn it_should_play_out_the_same_for_the_same_seed() {
+        let play = |seed| {
+            let mut game = game(Movement::Step);
+            game.set_seed(seed);
+            for tick in 0..400 {
+                if tick % 30 == 0 {
+                    game.act(&Action::Move(Direction::Up));
+                }
+                game.tick();
+            }
+
+            let mut npc = game
+                .map
+                .npc
+                .iter()
+                .map(|(id, point)| (*id, *point))
+                .collect::<Vec<_>>();
+            npc.sort();
+            (npc, game.map.current)
+        };
+
+        assert_eq!(play(3), play(3));
+    }
 }
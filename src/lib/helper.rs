@@ -1,16 +1,16 @@
-use crate::lib::shared::{ParsedMap, Point};
+use crate::{
+    shared::{ParsedMap, Point, Tile},
+    visual::{is_visual, parse_visual},
+};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{self, Display},
     str::FromStr,
     string::{String, ToString},
 };
 use termion::color::{self, Color};
 
-lazy_static! {
-    static ref EMPTY_STR: String = "".to_string();
-    static ref DEFAULT_INPUT: String = "0|0|0,0|0,0,0".to_string();
-}
-
 pub fn with_color<'a, C>(tile: &str, print_color: C) -> String
 where
     C: 'a + Color,
@@ -39,104 +39,647 @@ where
     input.next().unwrap_or(a).parse::<T>().unwrap_or(b)
 }
 
-fn start_end<'a, T>(input: &'a str, a: &'a str, b: T) -> (T, T)
-where
-    T: 'a + FromStr + ToString + Copy,
-{
-    let mut range = input.split("-");
-    let start = unwrap_and_parse(&mut range, a, b);
-    let start_str = start.to_string();
-    let end = unwrap_and_parse(&mut range, &start_str, b);
-    (start, end)
-}
-
-fn usize_iter<'a>(input: &'a str) -> impl Iterator<Item = usize> {
-    input
-        .split(",")
-        .map(|str| str.parse::<usize>().unwrap_or(0))
-        .collect::<Vec<_>>()
-        .into_iter()
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Field {
+    Header,
+    Props,
+    Tiles,
+    Grid,
+    Legend,
+    Trigger,
 }
 
-pub fn parse_maps(str: &str) -> Vec<ParsedMap> {
-    str.lines()
-        .map(str::trim_end)
-        .map(str::to_owned)
-        .collect::<Vec<_>>()
-        .chunks(3)
-        .filter(|chunk| {
-            let mut chunk = chunk.into_iter();
-            !chunk.next().unwrap_or(&EMPTY_STR).is_empty()
-        })
-        .map(|chunk| {
-            let mut input = chunk.into_iter();
-
-            let mut meta = unwrap_or(&mut input, &DEFAULT_INPUT).split("|");
-            let (area, part, size, player) = (
-                unwrap_and_parse(&mut meta, "0", 0),
-                unwrap_and_parse(&mut meta, "0", 0),
-                unwrap_or(&mut meta, "0,0"),
-                unwrap_or(&mut meta, "0,0,0"),
-            );
+impl Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Field::Header => write!(f, "header"),
+            Field::Props => write!(f, "props line"),
+            Field::Tiles => write!(f, "tile line"),
+            Field::Grid => write!(f, "grid"),
+            Field::Legend => write!(f, "legend"),
+            Field::Trigger => write!(f, "trigger line"),
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum MapErrorKind {
+    Number(String),
+    Missing(&'static str),
+    Unexpected(String),
+    Direction(u8),
+    UnknownTile(u8),
+    Overlap(Point),
+    OutOfBounds(Point),
+    Reversed(String),
+    PlayerOnWall,
+    WarpTarget(u8, u8),
+    NoMap(u8, u8),
+    PropsOutside(u8),
+    Duplicate(u8, u8),
+    NoLegend(Point),
+    NoGlyph(Point),
+}
+
+impl Display for MapErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapErrorKind::Number(text) => write!(f, "\"{}\" is not a number", text),
+            MapErrorKind::Missing(what) => write!(f, "missing {}", what),
+            MapErrorKind::Unexpected(text) => write!(f, "unexpected \"{}\"", text),
+            MapErrorKind::Direction(direction) => {
+                write!(f, "direction {} is not one of 0-3", direction)
+            }
+            MapErrorKind::UnknownTile(tile) => write!(f, "unknown tile {}", tile),
+            MapErrorKind::Overlap(point) => {
+                write!(
+                    f,
+                    "range overlaps an earlier one at {},{}",
+                    point.x, point.y
+                )
+            }
+            MapErrorKind::OutOfBounds(point) => {
+                write!(f, "{},{} is outside the map", point.x, point.y)
+            }
+            MapErrorKind::Reversed(text) => write!(f, "range \"{}\" ends before it starts", text),
+            MapErrorKind::PlayerOnWall => write!(f, "player starts on a wall"),
+            MapErrorKind::WarpTarget(area, part) => {
+                write!(f, "warp to {},{} which does not exist", area, part)
+            }
+            MapErrorKind::NoMap(area, part) => write!(f, "there is no map {},{}", area, part),
+            MapErrorKind::PropsOutside(id) => write!(f, "props range of {} is outside the map", id),
+            MapErrorKind::Duplicate(area, part) => {
+                write!(f, "map {},{} is defined twice", area, part)
+            }
+            MapErrorKind::NoLegend(point) => {
+                write!(
+                    f,
+                    "tile at {},{} needs a warp or npc entry",
+                    point.x, point.y
+                )
+            }
+            MapErrorKind::NoGlyph(point) => {
+                write!(f, "no matching tile at {},{} in the grid", point.x, point.y)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct MapError {
+    pub line: usize,
+    pub column: usize,
+    pub field: Field,
+    pub kind: MapErrorKind,
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} ({}): {}",
+            self.line, self.column, self.field, self.kind
+        )
+    }
+}
+
+impl Error for MapError {}
+
+#[derive(Clone, Copy)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub column: usize,
+}
+
+impl<'a> Token<'a> {
+    pub fn line(text: &'a str) -> Token<'a> {
+        let trimmed = text.trim();
+        let column = 1 + text.len() - text.trim_start().len();
+        Token {
+            text: trimmed,
+            column,
+        }
+    }
+
+    pub fn split(&self, separator: char) -> Vec<Token<'a>> {
+        let mut column = self.column;
+        self.text
+            .split(separator)
+            .map(|text| {
+                let token = Token { text, column };
+                column += text.chars().count() + 1;
+                token
+            })
+            .collect()
+    }
+
+    pub fn end(&self) -> usize {
+        self.column + self.text.chars().count()
+    }
+}
+
+pub struct Parser {
+    pub line: usize,
+    pub field: Field,
+    pub errors: Vec<MapError>,
+}
+
+///
+/// A parsed map, the first and last line it was written on, and where its
+/// header, player start and warps are, for the checks that need to know about
+/// every map.
+///
+pub struct Block {
+    pub map: ParsedMap,
+    pub lines: (usize, usize),
+    pub header: (usize, usize),
+    pub player: (usize, usize),
+    pub warps: Vec<(usize, usize, (u8, u8))>,
+}
+
+impl Parser {
+    pub fn error(&mut self, column: usize, kind: MapErrorKind) {
+        self.errors.push(MapError {
+            line: self.line,
+            column,
+            field: self.field,
+            kind,
+        });
+    }
+
+    pub fn number<T: FromStr>(&mut self, token: &Token) -> Option<T> {
+        match token.text.parse::<T>() {
+            Ok(number) => Some(number),
+            Err(_) => {
+                self.error(token.column, MapErrorKind::Number(token.text.to_string()));
+                None
+            }
+        }
+    }
+
+    pub fn values<'a>(
+        &mut self,
+        token: &Token<'a>,
+        separator: char,
+        names: &[&'static str],
+        required: usize,
+    ) -> Option<Vec<Token<'a>>> {
+        let values = token.split(separator);
+
+        if values.len() < required {
+            self.error(token.end(), MapErrorKind::Missing(names[values.len()]));
+            return None;
+        }
+
+        if let Some(extra) = values.get(names.len()) {
+            let text = token.text.chars().skip(extra.column - token.column);
+            let text = text.collect::<String>();
+            self.error(extra.column, MapErrorKind::Unexpected(text));
+            return None;
+        }
+
+        Some(values)
+    }
+
+    pub fn range<T: FromStr + Copy + PartialOrd>(&mut self, token: &Token) -> Option<(T, T)> {
+        let values = self.values(token, '-', &["start", "end"], 1)?;
+        let start = self.number(&values[0])?;
+        let end = match values.get(1) {
+            Some(end) => self.number(end)?,
+            None => start,
+        };
+
+        if end < start {
+            self.error(token.column, MapErrorKind::Reversed(token.text.to_string()));
+            return None;
+        }
+
+        Some((start, end))
+    }
 
-            let mut iter = usize_iter(size);
-            let (max_x, max_y) = (unwrap_or(&mut iter, 0), unwrap_or(&mut iter, 0));
+    fn header(&mut self, line: &Token) -> Option<(ParsedMap, usize)> {
+        let fields = self.values(line, '|', &["area", "part", "size", "player"], 4)?;
+        let (area, part) = (self.number(&fields[0]), self.number(&fields[1]));
 
-            let mut iter = usize_iter(player);
-            let (player_x, player_y, player_direction) = (
-                unwrap_or(&mut iter, 0),
-                unwrap_or(&mut iter, 0),
-                unwrap_or(&mut iter, 0) as u8,
+        let size = self.values(&fields[2], ',', &["width", "height"], 2)?;
+        let (max_x, max_y) = (self.number(&size[0]), self.number(&size[1]));
+
+        let player = self.values(&fields[3], ',', &["x", "y", "direction"], 3)?;
+        let (x, y, direction) = (
+            self.number(&player[0]),
+            self.number(&player[1]),
+            self.number(&player[2]),
+        );
+
+        if let Some(direction) = direction.filter(|direction| *direction > 3) {
+            self.error(player[2].column, MapErrorKind::Direction(direction));
+            return None;
+        }
+
+        Some((
+            ParsedMap {
+                area: area?,
+                part: part?,
+                grid: HashMap::new(),
+                max: (max_x?, max_y?),
+                player: (x?, y?, direction?),
+                props: HashMap::new(),
+            },
+            fields[3].column,
+        ))
+    }
+
+    fn props(&mut self, line: &Token, map: &mut ParsedMap) {
+        for segment in line.split('|').iter().filter(|s| !s.text.is_empty()) {
+            let values = match self.values(segment, ',', &["id", "x", "y"], 3) {
+                Some(values) => values,
+                None => continue,
+            };
+
+            let (id, x, y) = (
+                self.number::<u8>(&values[0]),
+                self.range::<u8>(&values[1]),
+                self.range::<u8>(&values[2]),
             );
 
-            let mut props = HashMap::new();
-            for prop in unwrap_or(&mut input, &EMPTY_STR).split("|") {
-                if prop.is_empty() {
-                    continue;
+            if let (Some(id), Some(x), Some(y)) = (id, x, y) {
+                let (max_x, max_y) = map.max;
+                if x.0.max(x.1) as usize >= max_x || y.0.max(y.1) as usize >= max_y {
+                    self.error(segment.column, MapErrorKind::PropsOutside(id));
                 }
+                map.props.insert(id, (x.0..=x.1, y.0..=y.1));
+            }
+        }
+    }
 
-                let mut iter = prop.split(",");
-                let (id, (x_start, x_end), (y_start, y_end)) = (
-                    unwrap_and_parse(&mut iter, "0", 0),
-                    start_end(unwrap_or(&mut iter, "0"), "0", 0),
-                    start_end(unwrap_or(&mut iter, "0"), "0", 0),
-                );
+    pub fn tiles(&mut self, line: &Token, map: &mut ParsedMap) -> Vec<(usize, (u8, u8))> {
+        let mut taken = HashSet::new();
+        let mut warps = Vec::new();
 
-                props.insert(id, (x_start..=x_end, y_start..=y_end));
+        for segment in line.split('|').iter().filter(|s| !s.text.is_empty()) {
+            let names = ["x", "y", "tile", "meta", "meta"];
+            let values = match self.values(segment, ',', &names, 2) {
+                Some(values) => values,
+                None => continue,
+            };
+
+            let numbers = values
+                .iter()
+                .skip(2)
+                .map(|value| self.number::<u8>(value))
+                .collect::<Vec<_>>();
+            let number = |i: usize| numbers.get(i).cloned().unwrap_or(Some(0));
+            let (tile, meta) = (number(0), (number(1), number(2)));
+
+            let (x, y) = (
+                self.range::<usize>(&values[0]),
+                self.range::<usize>(&values[1]),
+            );
+
+            let (x, y, tile, meta) = match (x, y, tile, meta) {
+                (Some(x), Some(y), Some(tile), (Some(a), Some(b))) => (x, y, tile, (a, b)),
+                _ => continue,
+            };
+
+            if Tile::from_u8(&tile) == Tile::Unknown {
+                self.error(values[2].column, MapErrorKind::UnknownTile(tile));
+                continue;
             }
 
-            let mut grid = HashMap::new();
-            for chars in unwrap_or(&mut input, &EMPTY_STR).split("|") {
-                if chars.is_empty() {
-                    continue;
-                }
+            // checked on the ends so that a typo can't make a huge range
+            if x.1 >= map.max.0 || y.1 >= map.max.1 {
+                let point = match y.1 >= map.max.1 {
+                    true => Point {
+                        x: x.0,
+                        y: y.0.max(map.max.1),
+                    },
+                    false => Point {
+                        x: x.0.max(map.max.0),
+                        y: y.0,
+                    },
+                };
+                self.error(segment.column, MapErrorKind::OutOfBounds(point));
+                continue;
+            }
 
-                let mut iter = chars.split(",");
-                let ((x_start, x_end), (y_start, y_end), tile, meta) = (
-                    start_end(unwrap_or(&mut iter, "0"), "0", 0),
-                    start_end(unwrap_or(&mut iter, "0"), "0", 0),
-                    unwrap_and_parse(&mut iter, "0", 0),
-                    (
-                        unwrap_and_parse(&mut iter, "0", 0),
-                        unwrap_and_parse(&mut iter, "0", 0),
-                    ),
-                );
-
-                for x in x_start..=x_end {
-                    for y in y_start..=y_end {
-                        grid.insert(Point { x, y }, (tile, meta));
-                    }
-                }
+            let is_range = x.0 != x.1 || y.0 != y.1;
+            let points = (x.0..=x.1)
+                .flat_map(|x| (y.0..=y.1).map(move |y| Point { x, y }))
+                .collect::<Vec<_>>();
+
+            // a single tile may replace part of a range, e.g. a warp in a wall
+            let overlap = points.iter().find(|point| taken.contains(*point));
+            if let Some(point) = overlap.filter(|_| is_range) {
+                self.error(segment.column, MapErrorKind::Overlap(point.to_owned()));
+                continue;
             }
 
-            ParsedMap {
-                area,
-                part,
-                grid,
-                max: (max_x, max_y),
-                player: (player_x, player_y, player_direction),
-                props,
-            }
-        })
-        .collect::<Vec<_>>()
+            if Tile::from_u8(&tile) == Tile::Warp {
+                warps.push((segment.column, meta));
+            }
+
+            for point in points {
+                taken.insert(point);
+                map.grid.insert(point, (tile, meta));
+            }
+        }
+
+        warps
+    }
+}
+
+fn is_header(line: &str) -> bool {
+    match line.trim().split('|').next() {
+        Some(first) => !first.is_empty() && !first.contains(','),
+        None => false,
+    }
+}
+
+fn empty_map() -> ParsedMap {
+    ParsedMap {
+        area: 0,
+        part: 0,
+        grid: HashMap::new(),
+        max: (usize::MAX, usize::MAX),
+        player: (0, 0, 0),
+        props: HashMap::new(),
+    }
+}
+
+fn compact(parser: &mut Parser, header: (usize, &str), body: &[(usize, &str)]) -> Option<Block> {
+    let (number, line) = header;
+    let header = Token::line(line);
+    parser.line = number;
+    parser.field = Field::Header;
+
+    let parsed = parser.header(&header);
+    let valid = parsed.is_some();
+    let (mut map, player_column) = parsed.unwrap_or_else(|| (empty_map(), 0));
+
+    let body = body
+        .iter()
+        .map(|(number, line)| (number.to_owned(), Token::line(line)))
+        .collect::<Vec<_>>();
+
+    let (props, tiles) = match body.as_slice() {
+        [] => {
+            parser.field = Field::Tiles;
+            parser.error(header.end(), MapErrorKind::Missing("tile line"));
+            return None;
+        }
+        [tiles] => (None, tiles),
+        [props, tiles] => (Some(props), tiles),
+        [_, _, extra, ..] => {
+            parser.line = extra.0;
+            parser.field = Field::Tiles;
+            let text = extra.1.text.to_string();
+            parser.error(extra.1.column, MapErrorKind::Unexpected(text));
+            return None;
+        }
+    };
+
+    if let Some((number, props)) = props {
+        parser.line = *number;
+        parser.field = Field::Props;
+        parser.props(props, &mut map);
+    }
+
+    parser.line = tiles.0;
+    parser.field = Field::Tiles;
+    let warps = parser
+        .tiles(&tiles.1, &mut map)
+        .into_iter()
+        .map(|(column, target)| (tiles.0, column, target))
+        .collect();
+
+    if !valid {
+        return None;
+    }
+
+    Some(Block {
+        map,
+        lines: (number, tiles.0),
+        header: (number, header.column),
+        player: (number, player_column),
+        warps,
+    })
+}
+
+///
+/// Every map that could be parsed, alongside the line its header is on, and
+/// every error found; `parse_maps` only cares about the first one.
+///
+pub fn parse_all(str: &str) -> (Vec<(usize, ParsedMap)>, Vec<MapError>) {
+    let (blocks, errors) = parse_blocks(str);
+    let maps = blocks
+        .into_iter()
+        .map(|block| (block.header.0, block.map))
+        .collect();
+
+    (maps, errors)
+}
+
+///
+/// Like `parse_all`, with the whole of every map parsed.
+///
+pub fn parse_blocks(str: &str) -> (Vec<Block>, Vec<MapError>) {
+    let mut parser = Parser {
+        line: 0,
+        field: Field::Header,
+        errors: Vec::new(),
+    };
+
+    let lines = str
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .collect::<Vec<_>>();
+
+    let mut blocks = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let (number, line) = lines[i];
+
+        if line.trim().is_empty() {
+            i += 1;
+            continue;
+        }
+
+        if is_visual(line) {
+            let end = lines[i..].iter().position(|(_, line)| line.trim() == "end");
+            let end = end.map(|end| i + end);
+            let block = &lines[i..end.unwrap_or(lines.len())];
+
+            blocks.push(parse_visual(&mut parser, block, end.is_some()));
+            i = end.unwrap_or(lines.len()) + 1;
+            continue;
+        }
+
+        let mut body = Vec::new();
+        i += 1;
+        while let Some((number, line)) = lines.get(i) {
+            if is_header(line) || is_visual(line) {
+                break;
+            }
+            if !line.trim().is_empty() {
+                body.push((number.to_owned(), line.to_owned()));
+            }
+            i += 1;
+        }
+
+        if !is_header(line) {
+            parser.line = number;
+            parser.field = Field::Header;
+            parser.error(Token::line(line).column, MapErrorKind::Missing("header"));
+            continue;
+        }
+
+        blocks.push(compact(&mut parser, (number, line), &body));
+    }
+
+    let mut parsed = Vec::new();
+    let mut seen: HashMap<(u8, u8), usize> = HashMap::new();
+    let mut warps = Vec::new();
+
+    for block in blocks.into_iter().flatten() {
+        let map = &block.map;
+        parser.field = Field::Header;
+
+        let player = Point {
+            x: map.player.0,
+            y: map.player.1,
+        };
+        parser.line = block.player.0;
+        if player.x >= map.max.0 || player.y >= map.max.1 {
+            parser.error(block.player.1, MapErrorKind::OutOfBounds(player));
+        } else if let Some((0, _)) = map.grid.get(&player) {
+            parser.error(block.player.1, MapErrorKind::PlayerOnWall);
+        }
+
+        let key = (map.area, map.part);
+        parser.line = block.header.0;
+        if seen.insert(key, block.header.0).is_some() {
+            parser.error(block.header.1, MapErrorKind::Duplicate(key.0, key.1));
+            continue;
+        }
+
+        warps.extend(block.warps.iter().cloned());
+        parsed.push(block);
+    }
+
+    parser.field = Field::Tiles;
+    for (line, column, target) in warps {
+        if !seen.contains_key(&target) {
+            parser.line = line;
+            parser.error(column, MapErrorKind::WarpTarget(target.0, target.1));
+        }
+    }
+
+    let mut errors = parser.errors;
+    errors.sort_by_key(|error| (error.line, error.column));
+
+    (parsed, errors)
+}
+
+///
+/// Parses maps in the range encoded format of `lib/maps`, three lines per map:
+///
+///     area|part|width,height|x,y,direction
+///     id,x-x,y-y|...                          props, may be left out
+///     x-x,y-y,tile,meta,meta|...
+///
+/// Blank lines are ignored. Ranges may not overlap, except for single tiles
+/// placed on top of a range. Maps in the format of `visual::parse_visual`,
+/// starting with a `map` line, can be mixed in with the others. Returns the
+/// first error found, by position.
+///
+pub fn parse_maps(str: &str) -> Result<Vec<ParsedMap>, MapError> {
+    let (maps, mut errors) = parse_all(str);
+
+    if errors.is_empty() {
+        Ok(maps.into_iter().map(|(_, map)| map).collect())
+    } else {
+        Err(errors.remove(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(str: &str) -> (usize, usize, Field, MapErrorKind) {
+        let error = parse_maps(str).err().unwrap();
+        (error.line, error.column, error.field, error.kind)
+    }
+
+    #[test]
+    fn it_should_parse_the_bundled_maps() {
+        let maps = parse_maps(include_str!("../../lib/maps")).unwrap();
+        assert_eq!(maps.len(), 2);
+        assert_eq!(maps[0].max, (25, 9));
+        assert_eq!(maps[0].props[&1], (14..=20, 4..=5));
+        assert_eq!(maps[0].grid[&Point { x: 15, y: 0 }], (3, (0, 1)));
+        assert_eq!(maps[1].player, (7, 4, 0));
+    }
+
+    #[test]
+    fn it_should_point_at_malformed_numbers() {
+        assert_eq!(
+            error("0|0|5,5|1,1,0\n0-4,0|0-4,4|0,1-x"),
+            (2, 17, Field::Tiles, MapErrorKind::Number("x".to_string()))
+        );
+        assert_eq!(
+            error("0|0|5,5\n0-4,0"),
+            (1, 8, Field::Header, MapErrorKind::Missing("player"))
+        );
+    }
+
+    #[test]
+    fn it_should_reject_overlapping_ranges_but_not_single_tiles() {
+        assert!(parse_maps("0|0|5,5|1,1,0\n0-4,0|2,0,3,0,0").is_ok());
+        assert_eq!(
+            error("0|0|5,5|1,1,0\n0-4,0|3-4,0-1"),
+            (
+                2,
+                7,
+                Field::Tiles,
+                MapErrorKind::Overlap(Point { x: 3, y: 0 })
+            )
+        );
+    }
+
+    #[test]
+    fn it_should_reject_huge_and_reversed_ranges() {
+        assert_eq!(
+            error("0|0|5,5|1,1,0\n0-99999999,0-99999999").3,
+            MapErrorKind::OutOfBounds(Point { x: 0, y: 5 })
+        );
+        assert_eq!(
+            error("0|0|5,5|1,1,0\n0-4,0|4-2,1"),
+            (
+                2,
+                7,
+                Field::Tiles,
+                MapErrorKind::Reversed("4-2".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn it_should_check_the_maps_make_sense() {
+        assert_eq!(error("0|0|5,5|0,0,0\n0-4,0").3, MapErrorKind::PlayerOnWall);
+        assert_eq!(
+            error("0|0|5,5|1,1,0\n2,2,3,1,0").3,
+            MapErrorKind::WarpTarget(1, 0)
+        );
+        assert_eq!(
+            error("0|0|5,5|1,1,0\n0,0-5,0-1\n0,0").3,
+            MapErrorKind::PropsOutside(0)
+        );
+        assert_eq!(
+            error("0|0|5,5|1,1,0\n0,0\n\n0|0|5,5|1,1,0\n0,0").3,
+            MapErrorKind::Duplicate(0, 0)
+        );
+    }
 }
@@ -0,0 +1,105 @@
+use crate::{
+    messages::MessageLog,
+    shared::{Direction, Point, Tile},
+};
+
+///
+/// How many of the latest messages are shown under the status lines.
+///
+pub const LOG_LINES: usize = 3;
+
+///
+/// The least room the status lines and log get, however narrow the map.
+///
+pub const HUD_WIDTH: usize = 40;
+
+pub fn describe(tile: Option<&Tile>) -> &'static str {
+    match tile {
+        Some(Tile::Wall) => "a wall",
+        Some(Tile::Empty) => "empty floor",
+        Some(Tile::Warp) => "a way out",
+        Some(Tile::NPC) => "someone",
+        Some(Tile::Grass) => "tall grass",
+        Some(Tile::Mud) => "mud",
+        _ => "nothing",
+    }
+}
+
+fn fit(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
+
+///
+/// Where the player is and what they face, cut to `width`.
+///
+pub fn status(
+    name: &str,
+    map: (u8, u8),
+    point: &Point,
+    direction: &Direction,
+    facing: Option<&Tile>,
+    width: usize,
+) -> Vec<String> {
+    let direction = format!("{:?}", direction).to_lowercase();
+    vec![
+        fit(&format!("{} ({},{})", name, map.0, map.1), width),
+        fit(
+            &format!(
+                "{},{} facing {}, {} ahead",
+                point.x,
+                point.y,
+                direction,
+                describe(facing)
+            ),
+            width,
+        ),
+    ]
+}
+
+///
+/// The latest `LOG_LINES` messages, oldest first, always that many lines so
+/// the screen doesn't jump as messages come in.
+///
+pub fn log_panel(log: &MessageLog, width: usize) -> Vec<String> {
+    let mut out = log
+        .window(LOG_LINES, 0)
+        .iter()
+        .map(|entry| entry.line(width))
+        .collect::<Vec<_>>();
+
+    out.resize(LOG_LINES, String::new());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Severity;
+
+    #[test]
+    fn it_should_describe_position_and_facing() {
+        let lines = status(
+            "The Cellar",
+            (0, 1),
+            &Point { x: 17, y: 7 },
+            &Direction::Up,
+            Some(&Tile::Wall),
+            30,
+        );
+
+        assert_eq!(lines[0], "The Cellar (0,1)");
+        assert_eq!(lines[1], "17,7 facing up, a wall ahead");
+    }
+
+    #[test]
+    fn it_should_keep_the_log_panel_at_a_fixed_height() {
+        let mut log = MessageLog::default();
+        log.push(0, Severity::Info, "one".to_string());
+        assert_eq!(log_panel(&log, 7), vec!["00:00 o", "", ""]);
+
+        for text in &["a", "b", "c"] {
+            log.push(1300, Severity::Info, text.to_string());
+        }
+        assert_eq!(log_panel(&log, 10), vec!["01:05 a", "01:05 b", "01:05 c"]);
+    }
+}
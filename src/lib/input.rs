@@ -0,0 +1,438 @@
+use crate::shared::Direction;
+use std::{collections::HashMap, env, path::PathBuf};
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    ShiftUp,
+    ShiftDown,
+    ShiftLeft,
+    ShiftRight,
+    Enter,
+    Tab,
+    Backspace,
+    Esc,
+}
+
+impl Key {
+    fn from_name(name: &str) -> Option<Key> {
+        let key = match name {
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "shift+up" => Key::ShiftUp,
+            "shift+down" => Key::ShiftDown,
+            "shift+left" => Key::ShiftLeft,
+            "shift+right" => Key::ShiftRight,
+            "enter" => Key::Enter,
+            "tab" => Key::Tab,
+            "backspace" => Key::Backspace,
+            "esc" => Key::Esc,
+            "space" => Key::Char(' '),
+            _ => {
+                let (ctrl, name) = match name.strip_prefix("ctrl+") {
+                    Some(name) => (true, name),
+                    None => (false, name),
+                };
+                let mut chars = name.chars();
+                match (chars.next(), chars.next(), ctrl) {
+                    (Some(char), None, false) => Key::Char(char),
+                    (Some(char), None, true) if char.is_ascii_lowercase() => Key::Ctrl(char),
+                    _ => return None,
+                }
+            }
+        };
+
+        Some(key)
+    }
+}
+
+///
+/// Turns the bytes read from a raw terminal into keys. Escape sequences may
+/// arrive split over several reads, so an unfinished one is held back until
+/// more bytes come or `idle` says none will.
+///
+#[derive(Default, Debug)]
+pub struct Decoder {
+    pending: Vec<u8>,
+}
+
+enum Decoded {
+    Key(Option<Key>, usize),
+    Partial,
+}
+
+fn csi(bytes: &[u8]) -> Decoded {
+    // ESC [ parameters final, the final byte is in 0x40..=0x7e
+    let end = match bytes[2..]
+        .iter()
+        .position(|byte| (0x40..=0x7e).contains(byte))
+    {
+        Some(end) => end + 2,
+        None => return Decoded::Partial,
+    };
+
+    // ESC [ 1 ; modifier final, only shift (2) is told apart, other
+    // modifiers are dropped along with the key
+    let key = match (&bytes[2..end], bytes[end]) {
+        (b"" | b"1", b'A') => Some(Key::Up),
+        (b"" | b"1", b'B') => Some(Key::Down),
+        (b"" | b"1", b'C') => Some(Key::Right),
+        (b"" | b"1", b'D') => Some(Key::Left),
+        (b"1;2", b'A') => Some(Key::ShiftUp),
+        (b"1;2", b'B') => Some(Key::ShiftDown),
+        (b"1;2", b'C') => Some(Key::ShiftRight),
+        (b"1;2", b'D') => Some(Key::ShiftLeft),
+        _ => None,
+    };
+
+    Decoded::Key(key, end + 1)
+}
+
+fn utf8(bytes: &[u8]) -> Decoded {
+    let len = match bytes[0] {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Decoded::Key(None, 1),
+    };
+    if bytes.len() < len {
+        return Decoded::Partial;
+    }
+
+    match std::str::from_utf8(&bytes[..len]) {
+        Ok(str) => Decoded::Key(str.chars().next().map(Key::Char), len),
+        Err(_) => Decoded::Key(None, 1),
+    }
+}
+
+fn decode(bytes: &[u8]) -> Decoded {
+    match bytes {
+        [27] => Decoded::Partial,
+        [27, b'[', ..] if bytes.len() == 2 => Decoded::Partial,
+        [27, b'[', ..] => csi(bytes),
+        [27, b'O'] => Decoded::Partial,
+        [27, b'O', final_byte, ..] => {
+            let key = match final_byte {
+                b'A' => Some(Key::Up),
+                b'B' => Some(Key::Down),
+                b'C' => Some(Key::Right),
+                b'D' => Some(Key::Left),
+                _ => None,
+            };
+            Decoded::Key(key, 3)
+        }
+        // alt+key, or escape pressed just before another key
+        [27, ..] => Decoded::Key(Some(Key::Esc), 1),
+        [13, ..] | [10, ..] => Decoded::Key(Some(Key::Enter), 1),
+        [9, ..] => Decoded::Key(Some(Key::Tab), 1),
+        [8, ..] | [127, ..] => Decoded::Key(Some(Key::Backspace), 1),
+        [byte @ 1..=26, ..] => Decoded::Key(Some(Key::Ctrl((b'a' + byte - 1) as char)), 1),
+        [byte @ 32..=126, ..] => Decoded::Key(Some(Key::Char(*byte as char)), 1),
+        [byte, ..] if *byte >= 0x80 => utf8(bytes),
+        _ => Decoded::Key(None, 1),
+    }
+}
+
+impl Decoder {
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Key> {
+        self.pending.extend_from_slice(bytes);
+
+        let mut keys = Vec::new();
+        let mut start = 0;
+        while start < self.pending.len() {
+            match decode(&self.pending[start..]) {
+                Decoded::Key(key, len) => {
+                    keys.extend(key);
+                    start += len;
+                }
+                Decoded::Partial => break,
+            }
+        }
+
+        self.pending.drain(..start);
+        keys
+    }
+
+    ///
+    /// Nothing more arrived, so whatever is held back was typed as is: a lone
+    /// escape, or the start of a sequence that was cut short.
+    ///
+    pub fn idle(&mut self) -> Vec<Key> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+
+        let rest = self.pending.split_off(1);
+        let mut keys = match self.pending[0] {
+            27 => vec![Key::Esc],
+            _ => Vec::new(),
+        };
+        self.pending.clear();
+        keys.extend(self.feed(&rest));
+        keys.extend(self.idle());
+        keys
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Action {
+    Move(Direction),
+    Turn(Direction),
+    Interact,
+    Confirm,
+    Choose(usize),
+    Save,
+    History,
+    Quit,
+}
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        let action = match name {
+            "up" => Action::Move(Direction::Up),
+            "down" => Action::Move(Direction::Down),
+            "left" => Action::Move(Direction::Left),
+            "right" => Action::Move(Direction::Right),
+            "turn-up" => Action::Turn(Direction::Up),
+            "turn-down" => Action::Turn(Direction::Down),
+            "turn-left" => Action::Turn(Direction::Left),
+            "turn-right" => Action::Turn(Direction::Right),
+            "interact" => Action::Interact,
+            "confirm" => Action::Confirm,
+            "save" => Action::Save,
+            "history" => Action::History,
+            "quit" => Action::Quit,
+            _ => match name.strip_prefix("choose-").map(str::parse::<usize>) {
+                Some(Ok(index @ 1..=9)) => Action::Choose(index - 1),
+                _ => return None,
+            },
+        };
+
+        Some(action)
+    }
+
+    ///
+    /// The name `from_name` reads back, as used in key bindings.
+    ///
+    pub fn name(&self) -> String {
+        let direction = |direction: &Direction| format!("{:?}", direction).to_lowercase();
+        match self {
+            Action::Move(to) => direction(to),
+            Action::Turn(to) => format!("turn-{}", direction(to)),
+            Action::Interact => "interact".to_string(),
+            Action::Confirm => "confirm".to_string(),
+            Action::Choose(index) => format!("choose-{}", index + 1),
+            Action::Save => "save".to_string(),
+            Action::History => "history".to_string(),
+            Action::Quit => "quit".to_string(),
+        }
+    }
+}
+
+const DEFAULT_KEYS: &str = "up = up w k
+down = down s j
+left = left a h
+right = right d l
+turn-up = shift+up W K
+turn-down = shift+down S J
+turn-left = shift+left A H
+turn-right = shift+right D L
+interact = space
+confirm = enter
+save = ctrl+s
+history = m
+quit = ctrl+c";
+
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: HashMap<Key, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+        };
+        keymap.apply(DEFAULT_KEYS).unwrap();
+        for index in 1..=9 {
+            let key = Key::Char((b'0' + index as u8) as char);
+            keymap.bindings.insert(key, Action::Choose(index - 1));
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    ///
+    /// The default bindings, with every action listed in `str` bound to the
+    /// keys given for it instead, one per line:
+    ///
+    ///     # vi keys only
+    ///     up = k
+    ///     down = j
+    ///     choose-1 = 1 ctrl+a
+    ///
+    pub fn parse(str: &str) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+        keymap.apply(str)?;
+        Ok(keymap)
+    }
+
+    fn apply(&mut self, str: &str) -> Result<(), String> {
+        for (i, line) in str.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |message: String| Err(format!("line {}: {}", i + 1, message));
+            let mut fields = line.splitn(2, '=');
+            let (name, keys) = match (fields.next(), fields.next()) {
+                (Some(name), Some(keys)) => (name.trim(), keys),
+                _ => return error(format!("expected \"action = keys\", got \"{}\"", line)),
+            };
+
+            let action = match Action::from_name(name) {
+                Some(action) => action,
+                None => return error(format!("unknown action \"{}\"", name)),
+            };
+
+            self.bindings.retain(|_, bound| bound != &action);
+            for name in keys.split_whitespace() {
+                match Key::from_name(name) {
+                    Some(key) => self.bindings.insert(key, action.to_owned()),
+                    None => return error(format!("unknown key \"{}\"", name)),
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn action(&self, key: &Key) -> Option<Action> {
+        self.bindings.get(key).cloned()
+    }
+}
+
+///
+/// What pressing a direction does when the player isn't facing it yet: only
+/// turn, so a second press steps, or turn and step at once.
+///
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum Movement {
+    #[default]
+    Classic,
+    Step,
+}
+
+impl Movement {
+    pub fn from_name(name: &str) -> Option<Movement> {
+        match name {
+            "classic" => Some(Movement::Classic),
+            "step" => Some(Movement::Step),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Movement::Classic => "classic",
+            Movement::Step => "step",
+        }
+    }
+}
+
+pub fn keys_path() -> PathBuf {
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".terminal-madness.keys"),
+        None => PathBuf::from("terminal-madness.keys"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_decode_sequences_split_over_reads() {
+        let mut decoder = Decoder::default();
+
+        assert_eq!(decoder.feed(b"a\x1b"), vec![Key::Char('a')]);
+        assert_eq!(decoder.feed(b"["), vec![]);
+        assert_eq!(
+            decoder.feed(b"Ab\x1bOD"),
+            vec![Key::Up, Key::Char('b'), Key::Left]
+        );
+        assert_eq!(decoder.feed("é\r\x13".as_bytes()), {
+            vec![Key::Char('é'), Key::Enter, Key::Ctrl('s')]
+        });
+    }
+
+    #[test]
+    fn it_should_decode_shift_arrows() {
+        let mut decoder = Decoder::default();
+
+        assert_eq!(
+            decoder.feed(b"\x1b[1;2A\x1b[1;2D\x1b[1;5C"),
+            vec![Key::ShiftUp, Key::ShiftLeft]
+        );
+        assert_eq!(
+            Keymap::default().action(&Key::ShiftUp),
+            Some(Action::Turn(Direction::Up))
+        );
+    }
+
+    #[test]
+    fn it_should_not_swallow_a_lone_escape() {
+        let mut decoder = Decoder::default();
+
+        assert_eq!(decoder.feed(b"\x1b"), vec![]);
+        assert_eq!(decoder.idle(), vec![Key::Esc]);
+
+        assert_eq!(decoder.feed(b"\x1b["), vec![]);
+        assert_eq!(decoder.idle(), vec![Key::Esc, Key::Char('[')]);
+        assert_eq!(decoder.feed(b"\x1bk"), vec![Key::Esc, Key::Char('k')]);
+    }
+
+    #[test]
+    fn it_should_bind_arrows_wasd_and_vi_keys_by_default() {
+        let keymap = Keymap::default();
+
+        for key in &[Key::Up, Key::Char('w'), Key::Char('k')] {
+            assert_eq!(keymap.action(key), Some(Action::Move(Direction::Up)));
+        }
+        assert_eq!(keymap.action(&Key::Char('3')), Some(Action::Choose(2)));
+        assert_eq!(keymap.action(&Key::Ctrl('c')), Some(Action::Quit));
+    }
+
+    #[test]
+    fn it_should_replace_the_bindings_of_configured_actions() {
+        let keymap = Keymap::parse("# arrows only\nup = up\nsave = ctrl+w tab").unwrap();
+
+        assert_eq!(keymap.action(&Key::Char('w')), None);
+        assert_eq!(keymap.action(&Key::Up), Some(Action::Move(Direction::Up)));
+        assert_eq!(keymap.action(&Key::Tab), Some(Action::Save));
+        assert_eq!(keymap.action(&Key::Ctrl('s')), None);
+
+        assert_eq!(
+            Keymap::parse("up = up\njump = space").err(),
+            Some("line 2: unknown action \"jump\"".to_string())
+        );
+        assert!(Keymap::parse("up = alt+up").is_err());
+    }
+
+    #[test]
+    fn it_should_read_back_action_names() {
+        let names = ["up", "turn-left", "interact", "choose-3", "history", "quit"];
+        for name in &names {
+            assert_eq!(Action::from_name(name).unwrap().name(), *name);
+        }
+    }
+}
@@ -1,38 +1,23 @@
-use crate::lib::{
+use crate::{
+    camera::Camera,
     helper::with_color,
+    navigation::Navigation,
+    render::Frame,
     shared::{Direction, Point, Tile},
 };
-use std::{
-    collections::{BTreeMap, HashMap},
-    ops::RangeInclusive,
-    string::String,
-};
-use termion::{clear, color, cursor};
+use std::{collections::HashMap, ops::RangeInclusive, string::String};
+use termion::color;
 
 lazy_static! {
     static ref TILE_WARP: String = with_color("░", color::Yellow);
+    static ref TILE_GRASS: String = with_color("\"", color::Green);
+    static ref TILE_MUD: String = with_color("~", color::LightBlack);
     static ref TILE_DIRECTION_LEFT: String = with_color("←", color::Green);
     static ref TILE_DIRECTION_RIGHT: String = with_color("→", color::Green);
     static ref TILE_DIRECTION_UP: String = with_color("↑", color::Green);
     static ref TILE_DIRECTION_DOWN: String = with_color("↓", color::Green);
 }
 
-fn grid_as_tree_map(
-    grid: &HashMap<Point, Tile>,
-    max_x: &usize,
-    max_y: &usize,
-) -> BTreeMap<Point, Tile> {
-    let mut tree_map = BTreeMap::new();
-    for y in 0..=(max_y + 1) {
-        for x in 0..=(max_x + 1) {
-            let point = Point { x, y };
-            let key = grid.get(&point).unwrap_or(&Tile::Unknown);
-            tree_map.insert(point, key.to_owned());
-        }
-    }
-    tree_map
-}
-
 fn print_tile_current(direction: &Direction) -> String {
     match direction {
         Direction::Left => TILE_DIRECTION_LEFT.to_string(),
@@ -42,13 +27,6 @@ fn print_tile_current(direction: &Direction) -> String {
     }
 }
 
-fn join<'a>(a: String, b: String) -> String {
-    let (a, b) = (a.to_owned(), b.to_owned());
-    let c = [a, b].concat();
-
-    c.to_string()
-}
-
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct Map {
     pub grid: HashMap<Point, Tile>,
@@ -56,6 +34,8 @@ pub struct Map {
     pub npc: HashMap<u8, Point>,
     pub direction: Direction,
     pub meta: HashMap<Point, (u8, u8)>,
+    pub terrain: HashMap<Point, Tile>,
+    pub navigation: Navigation,
     pub props: HashMap<u8, (RangeInclusive<u8>, RangeInclusive<u8>)>,
 }
 
@@ -82,9 +62,10 @@ impl Map {
 
         let mut meta = HashMap::new();
         let mut npc = HashMap::new();
+        let mut terrain = HashMap::new();
 
         for (point, (tile, tile_meta)) in input {
-            if let Some(grid) = grid.get_mut(&point) {
+            if let Some(grid) = grid.get_mut(point) {
                 let parsed_tile = Tile::from_u8(tile);
                 *grid = parsed_tile.to_owned();
 
@@ -92,70 +73,106 @@ impl Map {
                     npc.insert(tile_meta.0.to_owned(), point.to_owned());
                 }
 
+                if matches!(parsed_tile, Tile::Grass | Tile::Mud) {
+                    terrain.insert(point.to_owned(), parsed_tile);
+                }
+
                 meta.insert(point.to_owned(), tile_meta.to_owned());
             }
         }
 
         grid.insert(current.to_owned(), Tile::Current);
 
-        Map {
+        let mut map = Map {
             grid,
             current,
             meta,
             npc,
+            terrain,
+            navigation: Navigation::default(),
             props: props.to_owned(),
             direction: Direction::from_u8(player.2),
-        }
+        };
+        map.navigation = map.survey();
+        map
     }
 
-    pub fn print_grid(&mut self) {
-        let (max_x, max_y) = self.get_grid();
-        let mut current = 0;
-        let mut string = "".to_string();
-        let mut out = Vec::new();
-        for (point, tile) in grid_as_tree_map(&self.grid, &max_x, &max_y) {
-            if point.y != current {
-                out.push(string.to_owned());
-                string = "".to_string();
-                current = point.y.to_owned();
+    pub fn patch(&mut self, tiles: &HashMap<Point, (u8, (u8, u8))>) {
+        for (point, (tile, tile_meta)) in tiles {
+            let occupied = point == &self.current || self.npc.values().any(|npc| npc == point);
+            if occupied || !self.grid.contains_key(point) {
+                continue;
             }
 
-            string = join(
-                string,
-                match tile {
-                    Tile::Current => print_tile_current(&self.direction),
-                    Tile::Wall => "█".to_string(),
-                    Tile::Empty => " ".to_string(),
-                    Tile::Warp => TILE_WARP.to_string(),
-                    Tile::NPC => "X".to_string(),
-                    _ => " ".to_string(),
-                },
-            )
+            let tile = Tile::from_u8(tile);
+            match tile {
+                Tile::Grass | Tile::Mud => self.terrain.insert(point.to_owned(), tile),
+                _ => self.terrain.remove(point),
+            };
+
+            self.grid.insert(point.to_owned(), tile);
+            self.meta.insert(point.to_owned(), tile_meta.to_owned());
         }
 
-        print!(
-            "{}{}{}{}\r\n",
-            clear::All,
-            cursor::Goto(1, 1),
-            cursor::Hide,
-            out.join("\r\n")
-        );
+        self.navigation = self.survey();
     }
 
-    fn get_grid(&self) -> (usize, usize) {
-        let grid = self.grid.clone();
+    ///
+    /// Navigation for the ground, as if everyone had stepped off it.
+    ///
+    fn survey(&self) -> Navigation {
+        let ground = self
+            .grid
+            .iter()
+            .map(|(point, tile)| match tile {
+                Tile::Current | Tile::NPC => (point.to_owned(), self.ground(point)),
+                tile => (point.to_owned(), tile.to_owned()),
+            })
+            .collect();
+
+        Navigation::new(&ground)
+    }
 
-        let (x, y) = grid.iter().fold((0, 0), |mut acc, (point, _tile)| {
-            let (x, y) = (point.x, point.y);
-            if acc.0 < x {
-                acc.0 = x.to_owned();
-            }
-            if acc.1 < y {
-                acc.1 = y.to_owned();
-            }
-            acc
-        });
+    ///
+    /// What is left on `point` once whoever stands there moves on.
+    ///
+    pub fn ground(&self, point: &Point) -> Tile {
+        self.terrain.get(point).cloned().unwrap_or(Tile::Empty)
+    }
+
+    ///
+    /// The part of the grid `camera` sees as lines of glyphs, moved in by its
+    /// offset, followed by `below`, moved in as far as the screen allows.
+    ///
+    pub fn grid_lines(&self, camera: &Camera, below: &[String]) -> Vec<String> {
+        let pad = " ".repeat(camera.offset.0);
+        let (x, y) = (camera.origin.x, camera.origin.y);
+
+        let mut out = vec![String::new(); camera.offset.1];
+        for y in y..(y + camera.size.1) {
+            let row = (x..(x + camera.size.0))
+                .map(|x| match self.grid.get(&Point { x, y }) {
+                    Some(Tile::Current) => print_tile_current(&self.direction),
+                    Some(Tile::Wall) => "█".to_string(),
+                    Some(Tile::Warp) => TILE_WARP.to_string(),
+                    Some(Tile::NPC) => "X".to_string(),
+                    Some(Tile::Grass) => TILE_GRASS.to_string(),
+                    Some(Tile::Mud) => TILE_MUD.to_string(),
+                    _ => " ".to_string(),
+                })
+                .collect::<String>();
+            out.push(format!("{}{}", pad, row));
+        }
+
+        out.extend(below.iter().map(|line| {
+            let width = Frame::new(&[line.to_owned()]).size().0;
+            let pad = camera.offset.0.min(camera.width.saturating_sub(width));
+            format!("{}{}", " ".repeat(pad), line)
+        }));
+        out
+    }
 
-        (x, y)
+    pub fn size(&self) -> (usize, usize) {
+        self.navigation.size()
     }
 }
@@ -0,0 +1,170 @@
+use crate::{
+    events::{Event, Subscriber},
+    game::{Game, TICK},
+    helper::with_color,
+};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, time::Duration};
+use termion::color;
+
+///
+/// How many messages are kept for the history screen.
+///
+pub const CAPACITY: usize = 200;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Entry {
+    pub time: Duration,
+    pub severity: Severity,
+    pub text: String,
+}
+
+impl Entry {
+    ///
+    /// `mm:ss text`, cut to `width` and coloured by severity.
+    ///
+    pub fn line(&self, width: usize) -> String {
+        let seconds = self.time.as_secs();
+        let line = format!("{:02}:{:02} {}", seconds / 60, seconds % 60, self.text);
+        let line = line.chars().take(width).collect::<String>();
+
+        match self.severity {
+            Severity::Info => line,
+            Severity::Warning => with_color(&line, color::Yellow),
+            Severity::Error => with_color(&line, color::Red),
+        }
+    }
+}
+
+///
+/// Everything the game has told the player, timed by the game tick it was
+/// said on, oldest first.
+///
+#[derive(Clone, Debug, Default)]
+pub struct MessageLog {
+    entries: VecDeque<Entry>,
+}
+
+impl MessageLog {
+    ///
+    /// Adds `text` as said on tick `ticks`, so that a replay logs it at the
+    /// same time as the game it was recorded from.
+    ///
+    pub fn push(&mut self, ticks: u64, severity: Severity, text: String) {
+        if self.entries.len() == CAPACITY {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(Entry {
+            time: Duration::from_millis(ticks * TICK.as_millis() as u64),
+            severity,
+            text,
+        });
+    }
+
+    pub fn count(&self) -> usize {
+        self.entries.len()
+    }
+
+    ///
+    /// Up to `count` entries, ending `skip` entries before the latest.
+    ///
+    pub fn window(&self, count: usize, skip: usize) -> Vec<&Entry> {
+        let end = self.entries.len().saturating_sub(skip);
+        let start = end.saturating_sub(count);
+        self.entries.range(start..end).collect()
+    }
+}
+
+///
+/// Writes down everything the game says into a log the game reads back from.
+///
+pub struct Logger {
+    log: Rc<RefCell<MessageLog>>,
+}
+
+impl Logger {
+    pub fn new(log: Rc<RefCell<MessageLog>>) -> Logger {
+        Logger { log }
+    }
+}
+
+impl Subscriber for Logger {
+    fn notify(&mut self, event: &Event, _: &Game) {
+        if let Event::Said {
+            ticks,
+            severity,
+            text,
+        } = event
+        {
+            self.log
+                .borrow_mut()
+                .push(*ticks, *severity, text.to_owned());
+        }
+    }
+}
+
+///
+/// The full screen history, scrolled `offset` entries back from the latest.
+///
+pub fn history_screen(log: &MessageLog, offset: usize, screen: (usize, usize)) -> Vec<String> {
+    let rows = screen.1.saturating_sub(3).max(1);
+    let mut out = vec![format!("messages ({})", log.count()), String::new()];
+
+    out.extend(
+        log.window(rows, offset)
+            .iter()
+            .map(|entry| entry.line(screen.0)),
+    );
+    out.resize(rows + 2, String::new());
+    out.push("[up/down] scroll  [enter] close".to_string());
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(count: usize) -> MessageLog {
+        let mut log = MessageLog::default();
+        for i in 0..count {
+            log.push(i as u64 * 20, Severity::Info, i.to_string());
+        }
+        log
+    }
+
+    #[test]
+    fn it_should_drop_the_oldest_entries_past_capacity() {
+        let log = log(CAPACITY + 5);
+
+        assert_eq!(log.count(), CAPACITY);
+        assert_eq!(log.window(1, CAPACITY - 1)[0].text, "5");
+    }
+
+    #[test]
+    fn it_should_time_and_colour_entries() {
+        let entry = Entry {
+            time: Duration::from_secs(75),
+            severity: Severity::Error,
+            text: "could not save".to_string(),
+        };
+
+        assert_eq!(entry.line(8), with_color("01:15 co", color::Red));
+    }
+
+    #[test]
+    fn it_should_scroll_the_history() {
+        let screen = history_screen(&log(10), 2, (20, 6));
+
+        assert_eq!(screen.len(), 6);
+        assert_eq!(screen[0], "messages (10)");
+        assert_eq!(&screen[2..5], &["00:05 5", "00:06 6", "00:07 7"]);
+    }
+}
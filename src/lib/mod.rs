@@ -1,5 +1,26 @@
+#[macro_use]
+extern crate lazy_static;
+
+pub mod camera;
+pub mod check;
+pub mod cli;
+pub mod dialogue;
+pub mod editor;
+pub mod events;
+pub mod export;
+pub mod files;
+pub mod flags;
 pub mod game;
 pub mod helper;
+pub mod hud;
+pub mod input;
 pub mod map;
+pub mod messages;
+pub mod navigation;
 pub mod pathfinding;
+pub mod render;
+pub mod replay;
+pub mod save;
 pub mod shared;
+pub mod splash;
+pub mod visual;
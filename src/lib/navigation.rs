@@ -0,0 +1,197 @@
+use crate::shared::{Point, Tile};
+use std::collections::HashMap;
+
+///
+/// What finding a way across a map needs, worked out once when the map is
+/// parsed: the grid laid out flat, row by row, with what each point costs to
+/// step onto and which connected part of the map it belongs to.
+///
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Navigation {
+    width: usize,
+    height: usize,
+    costs: Vec<Option<usize>>,
+    components: Vec<Option<usize>>,
+}
+
+impl Navigation {
+    pub fn new(grid: &HashMap<Point, Tile>) -> Navigation {
+        let (width, height) = grid.keys().fold((0, 0), |size, point| {
+            (size.0.max(point.x + 1), size.1.max(point.y + 1))
+        });
+
+        let mut navigation = Navigation {
+            width,
+            height,
+            costs: vec![None; width * height],
+            components: vec![None; width * height],
+        };
+
+        for (point, tile) in grid {
+            navigation.costs[point.y * width + point.x] = tile.cost();
+        }
+
+        navigation.label();
+        navigation
+    }
+
+    ///
+    /// Gives every walkable point the number of the part of the map it is in,
+    /// flooding out from the first unlabelled one in reading order. Diagonal
+    /// steps never cut corners, so they can't join parts that straight steps
+    /// don't.
+    ///
+    fn label(&mut self) {
+        let mut next = 0;
+
+        for first in 0..self.costs.len() {
+            if self.costs[first].is_none() || self.components[first].is_some() {
+                continue;
+            }
+
+            let mut queue = vec![first];
+            self.components[first] = Some(next);
+            while let Some(index) = queue.pop() {
+                for step in &[(0, -1), (1, 0), (0, 1), (-1, 0)] {
+                    let neighbour = match self.neighbour(index, *step) {
+                        Some(neighbour) => neighbour,
+                        None => continue,
+                    };
+
+                    if self.costs[neighbour].is_some() && self.components[neighbour].is_none() {
+                        self.components[neighbour] = Some(next);
+                        queue.push(neighbour);
+                    }
+                }
+            }
+
+            next += 1;
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    ///
+    /// How many points the flat grid holds, `width * height`.
+    ///
+    pub fn cells(&self) -> usize {
+        self.costs.len()
+    }
+
+    pub fn index(&self, point: &Point) -> Option<usize> {
+        if point.x >= self.width || point.y >= self.height {
+            return None;
+        }
+
+        Some(point.y * self.width + point.x)
+    }
+
+    pub fn point(&self, index: usize) -> Point {
+        Point {
+            x: index % self.width,
+            y: index / self.width,
+        }
+    }
+
+    ///
+    /// The point `step` away from `index`, if it is on the map.
+    ///
+    pub fn neighbour(&self, index: usize, (x, y): (isize, isize)) -> Option<usize> {
+        let point = self.point(index);
+        let (x, y) = (point.x as isize + x, point.y as isize + y);
+        if x < 0 || y < 0 {
+            return None;
+        }
+
+        self.index(&Point {
+            x: x as usize,
+            y: y as usize,
+        })
+    }
+
+    ///
+    /// What stepping onto `index` costs, `None` for walls and what is off the
+    /// map.
+    ///
+    pub fn cost(&self, index: Option<usize>) -> Option<usize> {
+        self.costs.get(index?).cloned().flatten()
+    }
+
+    pub fn component(&self, point: &Point) -> Option<usize> {
+        self.components[self.index(point)?]
+    }
+
+    ///
+    /// Whether `to` can be walked to from `from`. `from` is where someone
+    /// stands, so it needn't be walkable itself as long as a point next to it
+    /// is.
+    ///
+    pub fn reachable(&self, from: &Point, to: &Point) -> bool {
+        let component = match self.component(to) {
+            Some(component) => component,
+            None => return false,
+        };
+
+        if self.component(from) == Some(component) {
+            return true;
+        }
+
+        let from = match self.index(from) {
+            Some(from) => from,
+            None => return false,
+        };
+
+        [(0, -1), (1, 0), (0, 1), (-1, 0)].iter().any(|step| {
+            self.neighbour(from, *step)
+                .and_then(|neighbour| self.components[neighbour])
+                == Some(component)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> HashMap<Point, Tile> {
+        rows.iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars().enumerate().map(move |(x, char)| {
+                    let tile = if char == '█' {
+                        Tile::Wall
+                    } else {
+                        Tile::Empty
+                    };
+                    (Point { x, y }, tile)
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_should_label_the_parts_of_a_map() {
+        let navigation = Navigation::new(&grid(&["  █ ", "███ ", " █  "]));
+        let component = |x, y| navigation.component(&Point { x, y });
+
+        assert_eq!(navigation.size(), (4, 3));
+        assert_eq!(component(0, 0), component(1, 0));
+        assert_eq!(component(3, 0), component(2, 2));
+        assert_ne!(component(0, 0), component(3, 0));
+        assert_ne!(component(0, 2), component(0, 0));
+        assert_eq!(component(2, 0), None);
+    }
+
+    #[test]
+    fn it_should_reach_from_where_someone_stands() {
+        let navigation = Navigation::new(&grid(&["█ █ "]));
+        let point = |x| Point { x, y: 0 };
+
+        assert!(navigation.reachable(&point(0), &point(1)));
+        assert!(!navigation.reachable(&point(1), &point(3)));
+        assert!(!navigation.reachable(&point(1), &point(2)));
+        assert!(!navigation.reachable(&point(1), &point(9)));
+    }
+}
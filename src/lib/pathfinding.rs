@@ -1,14 +1,91 @@
-use crate::lib::shared::{Point, Tile};
+use crate::{navigation::Navigation, shared::Point};
 use std::{
     cmp::Ordering,
     collections::{BinaryHeap, HashMap},
-    usize,
 };
 
+///
+/// Which points a step can reach: the four next to a point, or those and the
+/// four corners as well.
+///
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum Neighbours {
+    #[default]
+    Four,
+    Eight,
+}
+
+///
+/// What a step costs on plain floor, straight and diagonally; the tile
+/// stepped onto multiplies it.
+///
+const STRAIGHT: usize = 10;
+const DIAGONAL: usize = 14;
+
+///
+/// How many steps ahead reservations are kept, past that a route is planned
+/// as if nobody else was around.
+///
+pub const WINDOW: usize = 16;
+
+///
+/// Where everyone else will be, step by step from now, so a route can wait
+/// for them or go around them instead of walking into them.
+///
+#[derive(Clone, Debug, Default)]
+pub struct Reservations {
+    taken: HashMap<(Point, usize), usize>,
+    holders: usize,
+}
+
+impl Reservations {
+    ///
+    /// Someone staying on `point`, the player or an npc without a route.
+    ///
+    pub fn hold(&mut self, point: Point) {
+        self.reserve(point, &[]);
+    }
+
+    ///
+    /// Someone walking `path` from `start`, goal first as `find_path` gives
+    /// it, and staying on the goal once there.
+    ///
+    pub fn reserve(&mut self, start: Point, path: &[Point]) {
+        self.holders += 1;
+
+        let mut route = path.iter().rev();
+        let mut at = start;
+        for time in 0..=WINDOW {
+            self.taken.insert((at, time), self.holders);
+            at = route.next().cloned().unwrap_or(at);
+        }
+    }
+
+    pub fn is_free(&self, point: &Point, time: usize) -> bool {
+        !self.taken.contains_key(&(point.to_owned(), time))
+    }
+
+    ///
+    /// Whether stepping from `from` onto `to` after `time` swaps places with
+    /// someone coming the other way.
+    ///
+    fn crosses(&self, from: &Point, to: &Point, time: usize) -> bool {
+        match (
+            self.taken.get(&(to.to_owned(), time)),
+            self.taken.get(&(from.to_owned(), time + 1)),
+        ) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Eq, PartialEq)]
 struct State {
     cost: usize,
-    point: Point,
+    estimate: usize,
+    index: usize,
+    time: usize,
 }
 
 impl Ord for State {
@@ -16,7 +93,9 @@ impl Ord for State {
         other
             .cost
             .cmp(&self.cost)
-            .then_with(|| self.point.cmp(&other.point))
+            .then_with(|| other.estimate.cmp(&self.estimate))
+            .then_with(|| other.index.cmp(&self.index))
+            .then_with(|| other.time.cmp(&self.time))
     }
 }
 
@@ -26,122 +105,191 @@ impl PartialOrd for State {
     }
 }
 
-type Map = HashMap<Point, Tile>;
-
-fn distance(p1: &Point, p2: &Point) -> f64 {
-    ((p2.x as f64 - p1.x as f64).powf(2f64) + (p2.y as f64 - p1.y as f64).powf(2f64)).sqrt()
+///
+/// The cheapest a path from `from` to `to` could be, every step on plain
+/// floor and nothing in the way.
+///
+fn estimate(from: &Point, to: &Point, neighbours: Neighbours) -> usize {
+    let (x, y) = (
+        (from.x as isize - to.x as isize).unsigned_abs(),
+        (from.y as isize - to.y as isize).unsigned_abs(),
+    );
+
+    match neighbours {
+        Neighbours::Four => STRAIGHT * (x + y),
+        Neighbours::Eight => STRAIGHT * x.max(y) + (DIAGONAL - STRAIGHT) * x.min(y),
+    }
 }
 
-pub fn adjacent(map: &Map, point: &Point) -> Vec<Point> {
-    let mut tiles = Vec::new();
+pub fn adjacent(navigation: &Navigation, point: &Point) -> Vec<Point> {
+    let index = match navigation.index(point) {
+        Some(index) => index,
+        None => return vec![],
+    };
+
+    steps(navigation, index, Neighbours::Four)
+        .into_iter()
+        .map(|(index, _)| navigation.point(index))
+        .collect()
+}
 
-    let mut vec: Vec<(isize, isize)> = vec![(0, 1), (1, 0)];
+///
+/// Every point one step from `index` that can be walked onto, with what the
+/// step costs. A diagonal step needs both points it passes between to be
+/// walkable, so corners of walls are never cut.
+///
+fn steps(navigation: &Navigation, index: usize, neighbours: Neighbours) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    let step = |offset| {
+        let next = navigation.neighbour(index, offset);
+        Some((next?, navigation.cost(next)?))
+    };
 
-    if point.x > 0 {
-        vec.push((-1, 0));
+    for offset in &[(0, -1), (1, 0), (0, 1), (-1, 0)] {
+        if let Some((next, cost)) = step(*offset) {
+            out.push((next, cost * STRAIGHT));
+        }
     }
 
-    if point.y > 0 {
-        vec.push((0, -1));
+    if neighbours == Neighbours::Four {
+        return out;
     }
 
-    for (x, y) in vec {
-        let new_pos = Point {
-            x: (point.x as isize + x) as usize,
-            y: (point.y as isize + y) as usize,
-        };
-        if let Some(tile) = map.get(&new_pos) {
-            if tile != &Tile::Wall {
-                tiles.push(new_pos.to_owned());
-            }
-        };
+    for (x, y) in &[(1, -1), (1, 1), (-1, 1), (-1, -1)] {
+        if step((*x, 0)).is_none() || step((0, *y)).is_none() {
+            continue;
+        }
+
+        if let Some((next, cost)) = step((*x, *y)) {
+            out.push((next, cost * DIAGONAL));
+        }
     }
 
-    tiles
+    out
 }
 
-pub fn find_path(map: &Map, start: Point, goal: Point) -> Vec<Point> {
-    let can_move = |point: &Point| -> bool {
-        match map.get(&point) {
-            Some(tile) => tile != &Tile::Wall,
-            None => false,
-        }
+///
+/// The cheapest way from `start` to `goal`, A* over what each tile costs to
+/// step onto and, for the first `WINDOW` steps, over time: a point someone
+/// else has reserved is waited for or gone around. A wait shows up as the
+/// same point twice. The path comes back goal first and without `start`, so
+/// the next step is the last point; it is empty when `goal` can't be reached,
+/// which the map's navigation tells without searching.
+///
+pub fn find_path(
+    navigation: &Navigation,
+    start: Point,
+    goal: Point,
+    neighbours: Neighbours,
+    reservations: &Reservations,
+) -> Vec<Point> {
+    if !navigation.reachable(&start, &goal) {
+        return vec![];
+    }
+
+    let (start, goal) = match (navigation.index(&start), navigation.index(&goal)) {
+        (Some(start), Some(goal)) => (start, goal),
+        _ => return vec![],
+    };
+    let estimate = |index| {
+        estimate(
+            &navigation.point(index),
+            &navigation.point(goal),
+            neighbours,
+        )
+    };
+
+    // with nobody to avoid, time doesn't matter and is left past the window
+    let (time, layers) = if reservations.taken.is_empty() {
+        (WINDOW + 1, 1)
+    } else {
+        (0, WINDOW + 2)
     };
 
+    // costs and steps back are kept flat, one grid of the map per step in time
+    let cells = navigation.cells();
+    let slot = |index: usize, time: usize| time.min(layers - 1) * cells + index;
+
     let mut frontier = BinaryHeap::new();
-    let mut dist: HashMap<Point, usize> = HashMap::new();
+    let mut best: Vec<Option<usize>> = vec![None; cells * layers];
+    let mut came_from: Vec<Option<(usize, usize)>> = vec![None; cells * layers];
 
     frontier.push(State {
-        cost: 0,
-        point: start,
+        cost: estimate(start),
+        estimate: estimate(start),
+        index: start,
+        time,
     });
-
-    let mut came_from = HashMap::new();
-    came_from.insert(start, None);
-
-    dist.insert(start.to_owned(), 0);
-
-    while let Some(State { point, cost }) = frontier.pop() {
-        if point == goal {
+    best[slot(start, time)] = Some(0);
+
+    let mut found = None;
+    while let Some(State {
+        index, cost, time, ..
+    }) = frontier.pop()
+    {
+        if index == goal {
+            found = Some((index, time));
             break;
         }
 
-        let to_point = dist.entry(point.to_owned()).or_insert(usize::MAX);
-
-        if cost > *to_point {
+        let so_far = best[slot(index, time)].unwrap_or_default();
+        if cost > so_far + estimate(index) {
             continue;
         }
 
-        for edge in adjacent(map, &point) {
-            if !came_from.contains_key(&edge) && can_move(&edge) {
-                let next_cost = distance(&goal, &edge) as usize;
-
-                let prev_cost = dist.entry(edge.to_owned()).or_insert(usize::MAX);
+        let next_time = (time + 1).min(WINDOW + 1);
+        let mut moves = steps(navigation, index, neighbours);
+        if next_time <= WINDOW {
+            moves.push((index, STRAIGHT));
+        }
 
-                if next_cost > *prev_cost {
+        for (next, step) in moves {
+            if next_time <= WINDOW {
+                let (point, to) = (navigation.point(index), navigation.point(next));
+                if !reservations.is_free(&to, next_time) || reservations.crosses(&point, &to, time)
+                {
                     continue;
                 }
-
-                frontier.push(State {
-                    point: edge,
-                    cost: next_cost,
-                });
-
-                came_from.insert(edge, Some(point));
-                *prev_cost = next_cost;
             }
-        }
-    }
 
-    let mut current = goal;
-    let mut path = vec![current];
-    while current != start {
-        match came_from.get(&current) {
-            Some(c) => {
-                if c.is_none() {
-                    return vec![];
-                }
-
-                current = c.unwrap();
+            let key = slot(next, next_time);
+            let next_cost = so_far + step;
+            if best[key].is_some_and(|cost| cost <= next_cost) {
+                continue;
+            }
 
-                if current == start {
-                    continue;
-                }
+            best[key] = Some(next_cost);
+            came_from[key] = Some((index, time));
 
-                path.push(current);
-            }
-            _ => return vec![],
+            let left = estimate(next);
+            frontier.push(State {
+                cost: next_cost + left,
+                estimate: left,
+                index: next,
+                time: next_time,
+            });
         }
     }
 
+    let mut path = Vec::new();
+    let mut current = match found {
+        Some(found) => found,
+        None => return path,
+    };
+    while let Some(previous) = came_from[slot(current.0, current.1)] {
+        path.push(navigation.point(current.0));
+        current = previous;
+    }
+
     path
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shared::Tile;
 
-    fn parse_map(input: &String) -> HashMap<Point, Tile> {
+    fn parse_grid(input: &str) -> HashMap<Point, Tile> {
         input
             .lines()
             .map(str::trim)
@@ -149,7 +297,7 @@ mod tests {
             .map(str::to_owned)
             .map(|line| line.chars().filter(|char| char != &'|').collect::<String>())
             .enumerate()
-            .map(|(y, line)| {
+            .flat_map(|(y, line)| {
                 line.chars()
                     .enumerate()
                     .map(|(x, char)| {
@@ -159,19 +307,74 @@ mod tests {
                                 '█' => Tile::Wall,
                                 'C' => Tile::Current,
                                 'X' => Tile::NPC,
+                                'g' => Tile::Grass,
+                                'm' => Tile::Mud,
+                                'w' => Tile::Warp,
                                 _ => Tile::Empty,
                             },
                         )
                     })
                     .collect::<Vec<_>>()
             })
-            .flatten()
+            .collect()
+    }
+
+    fn parse_map(input: &str) -> Navigation {
+        Navigation::new(&parse_grid(input))
+    }
+
+    ///
+    /// What walking `path` from `start` costs, failing on any point that isn't
+    /// a single step from the one before it.
+    ///
+    fn walk(map: &Navigation, start: Point, path: &[Point], neighbours: Neighbours) -> usize {
+        let mut at = start;
+        let mut total = 0;
+        for next in path.iter().rev() {
+            let (_, cost) = steps(map, map.index(&at).unwrap(), neighbours)
+                .into_iter()
+                .find(|(index, _)| map.point(*index) == *next)
+                .expect("a step to a neighbour");
+            total += cost;
+            at = *next;
+        }
+        total
+    }
+
+    ///
+    /// The cheapest cost from `start` to every point, found by relaxing every
+    /// step until nothing gets cheaper; slow, but plainly right.
+    ///
+    fn cheapest(map: &Navigation, start: Point, neighbours: Neighbours) -> HashMap<Point, usize> {
+        let mut costs = HashMap::new();
+        costs.insert(map.index(&start).unwrap(), 0);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in 0..map.size().0 * map.size().1 {
+                let here = match costs.get(&index) {
+                    Some(cost) => *cost,
+                    None => continue,
+                };
+                for (next, step) in steps(map, index, neighbours) {
+                    if costs.get(&next).is_none_or(|cost| *cost > here + step) {
+                        costs.insert(next, here + step);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        costs
+            .into_iter()
+            .map(|(index, cost)| (map.point(index), cost))
             .collect()
     }
 
     #[test]
     ///
-    /// Using Dijkstras algoritm; the prop should find the shortest route
+    /// Using A*; the prop should find the shortest route
     /// between `1,1` to `4,4`. There should be, _at the most_, 6 steps.
     ///
     ///     0,0 1,0 2,0 3,0 4,0
@@ -193,7 +396,14 @@ mod tests {
         let end = Point { x: 4, y: 4 };
 
         assert_eq!(
-            find_path(&parse_map(&example.to_string()), start, end).len(),
+            find_path(
+                &parse_map(example),
+                start,
+                end,
+                Neighbours::Four,
+                &Reservations::default()
+            )
+            .len(),
             6
         );
     }
@@ -208,11 +418,18 @@ mod tests {
             █  █████
         ";
 
-        let start = Point { x: 0, y: 1 };
-        let end = Point { x: 2, y: 3 };
+        let start = Point { x: 1, y: 1 };
+        let end = Point { x: 3, y: 3 };
 
         assert_eq!(
-            find_path(&parse_map(&example.to_string()), start, end).len(),
+            find_path(
+                &parse_map(example),
+                start,
+                end,
+                Neighbours::Four,
+                &Reservations::default()
+            )
+            .len(),
             12
         );
     }
@@ -234,8 +451,214 @@ mod tests {
         let end = Point { x: 4, y: 1 };
 
         assert_eq!(
-            find_path(&parse_map(&example.to_string()), start, end).len(),
+            find_path(
+                &parse_map(example),
+                start,
+                end,
+                Neighbours::Four,
+                &Reservations::default()
+            )
+            .len(),
             4
         );
     }
+
+    #[test]
+    fn it_should_find_the_cheapest_route_on_every_fixture() {
+        let fixtures = [
+            (
+                "
+                █     █
+                █ S   █
+                █     █
+                █     █
+                █    E█
+            ",
+                Point { x: 1, y: 1 },
+                Point { x: 4, y: 4 },
+            ),
+            (
+                "
+                █    ███
+                █S █   █
+                █  ███ █
+                █ █E   █
+                █  █████
+            ",
+                Point { x: 1, y: 1 },
+                Point { x: 3, y: 3 },
+            ),
+            (
+                "
+                ████████
+                █  XX  █
+                █S C E █
+                █  XX  █
+                █  ██  █
+                ████████
+            ",
+                Point { x: 0, y: 1 },
+                Point { x: 4, y: 1 },
+            ),
+        ];
+
+        for (example, start, end) in fixtures.iter().cloned() {
+            let map = parse_map(example);
+            for neighbours in &[Neighbours::Four, Neighbours::Eight] {
+                let path = find_path(&map, start, end, *neighbours, &Reservations::default());
+
+                assert_eq!(path.first(), Some(&end));
+                assert_eq!(
+                    walk(&map, start, &path, *neighbours),
+                    cheapest(&map, start, *neighbours)[&end]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_go_around_warps() {
+        let example = "
+            █████
+            █SwE█
+            █   █
+            █████
+        ";
+
+        let start = Point { x: 1, y: 1 };
+        let end = Point { x: 3, y: 1 };
+        let warp = Point { x: 2, y: 1 };
+
+        let path = find_path(
+            &parse_map(example),
+            start,
+            end,
+            Neighbours::Four,
+            &Reservations::default(),
+        );
+
+        assert_eq!(path.len(), 4);
+        assert!(!path.contains(&warp));
+    }
+
+    #[test]
+    fn it_should_cut_across_open_ground_diagonally() {
+        let map = parse_map(
+            "
+                █     █
+                █ S   █
+                █     █
+                █     █
+                █    E█
+            ",
+        );
+        let path = find_path(
+            &map,
+            Point { x: 1, y: 1 },
+            Point { x: 4, y: 4 },
+            Neighbours::Eight,
+            &Reservations::default(),
+        );
+
+        assert_eq!(path.len(), 3);
+    }
+
+    #[test]
+    fn it_should_not_cut_the_corners_of_walls() {
+        let example = "
+            ████
+            █S██
+            █ E█
+            ████
+        ";
+        let (start, end) = (Point { x: 1, y: 1 }, Point { x: 2, y: 2 });
+        let path = find_path(
+            &parse_map(example),
+            start,
+            end,
+            Neighbours::Eight,
+            &Reservations::default(),
+        );
+
+        assert_eq!(path, vec![end, Point { x: 1, y: 2 }]);
+    }
+
+    #[test]
+    fn it_should_walk_around_slow_ground_when_it_is_cheaper() {
+        let example = "
+            ████████
+            █S mm E█
+            █  mm  █
+            █      █
+            ████████
+        ";
+        let (grid, map) = (parse_grid(example), parse_map(example));
+        let (start, end) = (Point { x: 1, y: 1 }, Point { x: 6, y: 1 });
+
+        let path = find_path(&map, start, end, Neighbours::Four, &Reservations::default());
+        assert!(path.iter().all(|point| grid[point] != Tile::Mud));
+        assert_eq!(walk(&map, start, &path, Neighbours::Four), 90);
+
+        let map = parse_map(&example.replace('m', "g"));
+        let path = find_path(&map, start, end, Neighbours::Four, &Reservations::default());
+        assert_eq!(path.len(), 5);
+        assert_eq!(walk(&map, start, &path, Neighbours::Four), 70);
+    }
+
+    #[test]
+    fn it_should_give_up_on_goals_that_can_not_be_reached() {
+        let map = parse_map("█S█E█");
+        let start = Point { x: 1, y: 0 };
+
+        assert!(find_path(
+            &map,
+            start,
+            Point { x: 3, y: 0 },
+            Neighbours::Eight,
+            &Reservations::default()
+        )
+        .is_empty());
+        assert!(find_path(
+            &map,
+            start,
+            Point { x: 2, y: 0 },
+            Neighbours::Four,
+            &Reservations::default()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn it_should_step_out_of_the_way_of_reserved_routes() {
+        let example = "
+            ██ ████
+            █     █
+            ███████
+        ";
+        let map = parse_map(example);
+        let (start, end) = (Point { x: 1, y: 1 }, Point { x: 5, y: 1 });
+
+        let other = find_path(&map, end, start, Neighbours::Four, &Reservations::default());
+        let mut reservations = Reservations::default();
+        reservations.reserve(end, &other);
+
+        let path = find_path(&map, start, end, Neighbours::Four, &reservations);
+        let timeline = |start: Point, path: &[Point]| {
+            let mut out = vec![start];
+            out.extend(path.iter().rev());
+            out.resize(WINDOW, out[out.len() - 1]);
+            out
+        };
+        let (ours, theirs) = (timeline(start, &path), timeline(end, &other));
+
+        assert_eq!(path.first(), Some(&end));
+        assert_eq!(path.len(), 7);
+        assert!(ours.contains(&Point { x: 2, y: 0 }));
+        for time in 0..WINDOW {
+            assert_ne!(ours[time], theirs[time]);
+            if time > 0 {
+                assert!(ours[time] != theirs[time - 1] || ours[time - 1] != theirs[time]);
+            }
+        }
+    }
 }
@@ -0,0 +1,371 @@
+use crate::{
+    camera::screen_size,
+    events::{Event, Subscriber},
+    game::Game,
+};
+use std::{
+    cell::RefCell,
+    io::{stdout, Write},
+    rc::Rc,
+};
+use termion::{clear, color, cursor, terminal_size};
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Cell {
+    style: String,
+    glyph: char,
+}
+
+static BLANK: Cell = Cell {
+    style: String::new(),
+    glyph: ' ',
+};
+
+impl Cell {
+    fn draw(&self, out: &mut String) {
+        if self.style.is_empty() {
+            out.push(self.glyph);
+        } else {
+            out.push_str(&format!(
+                "{}{}{}",
+                self.style,
+                self.glyph,
+                color::Fg(color::Reset)
+            ));
+        }
+    }
+}
+
+///
+/// A screen of cells, each one glyph with the colour it was drawn in, so that
+/// two frames can be compared cell by cell.
+///
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Frame {
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Frame {
+    ///
+    /// Splits lines as printed, colour escapes included, into cells; a colour
+    /// holds until the next one or a reset.
+    ///
+    pub fn new(lines: &[String]) -> Frame {
+        let rows = lines
+            .iter()
+            .map(|line| {
+                let mut style = String::new();
+                let mut cells = Vec::new();
+                let mut chars = line.chars();
+
+                while let Some(glyph) = chars.next() {
+                    if glyph != '\x1b' {
+                        cells.push(Cell {
+                            style: style.to_owned(),
+                            glyph,
+                        });
+                        continue;
+                    }
+
+                    let mut escape = glyph.to_string();
+                    for next in chars.by_ref() {
+                        escape.push(next);
+                        if next.is_ascii_alphabetic() {
+                            break;
+                        }
+                    }
+
+                    if escape == color::Fg(color::Reset).to_string() || escape == "\x1b[0m" {
+                        style.clear();
+                    } else if escape.ends_with('m') {
+                        style = escape;
+                    }
+                }
+
+                cells
+            })
+            .collect();
+
+        Frame { rows }
+    }
+
+    fn get(&self, x: usize, y: usize) -> &Cell {
+        self.rows
+            .get(y)
+            .and_then(|row| row.get(x))
+            .unwrap_or(&BLANK)
+    }
+
+    fn width(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width(), self.rows.len())
+    }
+
+    ///
+    /// Each row as text, without its colours.
+    ///
+    pub fn plain(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.glyph).collect())
+            .collect()
+    }
+
+    ///
+    /// Each row as it would be printed, colours included.
+    ///
+    pub fn ansi(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let mut out = String::new();
+                for cell in row {
+                    cell.draw(&mut out);
+                }
+                out
+            })
+            .collect()
+    }
+
+    fn full(&self) -> String {
+        let mut out = format!("{}{}{}", clear::All, cursor::Goto(1, 1), cursor::Hide);
+        for (y, row) in self.rows.iter().enumerate() {
+            if y > 0 {
+                out.push_str("\r\n");
+            }
+            for cell in row {
+                cell.draw(&mut out);
+            }
+        }
+        out
+    }
+
+    ///
+    /// Only the cells that differ from `previous`, each run of them prefixed
+    /// by a `cursor::Goto`; cells that are gone are blanked.
+    ///
+    fn diff(&self, previous: &Frame) -> String {
+        let mut out = String::new();
+        let height = self.rows.len().max(previous.rows.len());
+        let width = self.width().max(previous.width());
+
+        for y in 0..height {
+            let mut at = None;
+            for x in 0..width {
+                let cell = self.get(x, y);
+                if cell == previous.get(x, y) {
+                    continue;
+                }
+
+                if at != Some(x) {
+                    out.push_str(&cursor::Goto(x as u16 + 1, y as u16 + 1).to_string());
+                }
+                cell.draw(&mut out);
+                at = Some(x + 1);
+            }
+        }
+
+        out
+    }
+}
+
+///
+/// Where the game draws its screens to.
+///
+pub trait Renderer {
+    ///
+    /// How much room there is to draw in, as columns and rows.
+    ///
+    fn size(&self) -> (usize, usize);
+
+    fn draw(&mut self, lines: &[String]);
+
+    ///
+    /// Forgets what was drawn, for when it was drawn over.
+    ///
+    fn invalidate(&mut self) {}
+
+    ///
+    /// Shows the cursor on column `x` and row `y`, counted from 1.
+    ///
+    fn show_cursor(&mut self, _x: u16, _y: u16) {}
+}
+
+///
+/// Keeps the last frame it drew and only sends what changed since, unless
+/// the terminal was resized or a full redraw was asked for.
+///
+#[derive(Clone, Debug, Default)]
+pub struct Terminal {
+    previous: Option<Frame>,
+    size: Option<(u16, u16)>,
+}
+
+impl Terminal {
+    fn update(&mut self, frame: Frame) -> String {
+        let size = terminal_size().ok();
+        if size != self.size {
+            self.size = size;
+            self.previous = None;
+        }
+
+        let out = match &self.previous {
+            Some(previous) => frame.diff(previous),
+            None => frame.full(),
+        };
+
+        self.previous = Some(frame);
+        out
+    }
+}
+
+impl Renderer for Terminal {
+    fn size(&self) -> (usize, usize) {
+        screen_size()
+    }
+
+    fn draw(&mut self, lines: &[String]) {
+        let out = self.update(Frame::new(lines));
+        if out.is_empty() {
+            return;
+        }
+
+        print!("{}", out);
+        let _ = stdout().flush();
+    }
+
+    fn invalidate(&mut self) {
+        self.previous = None;
+    }
+
+    fn show_cursor(&mut self, x: u16, y: u16) {
+        print!("{}{}", cursor::Goto(x, y), cursor::Show);
+        let _ = stdout().flush();
+    }
+}
+
+///
+/// Draws into memory instead of a terminal, a screen of `size` that is
+/// always there. Clones share the frame drawn, so one can be kept to look
+/// at what is drawn into another.
+///
+#[derive(Clone, Debug)]
+pub struct Buffer {
+    size: (usize, usize),
+    frame: Rc<RefCell<Frame>>,
+}
+
+impl Default for Buffer {
+    fn default() -> Buffer {
+        Buffer::new((80, 24))
+    }
+}
+
+impl Buffer {
+    pub fn new(size: (usize, usize)) -> Buffer {
+        Buffer {
+            size,
+            frame: Rc::new(RefCell::new(Frame::default())),
+        }
+    }
+
+    pub fn frame(&self) -> Frame {
+        self.frame.borrow().to_owned()
+    }
+}
+
+impl Renderer for Buffer {
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn draw(&mut self, lines: &[String]) {
+        *self.frame.borrow_mut() = Frame::new(lines);
+    }
+}
+
+///
+/// Draws the game with `renderer` each time it changed, from scratch after a
+/// warp or a resize.
+///
+pub struct Screen<R: Renderer> {
+    renderer: R,
+}
+
+impl<R: Renderer> Screen<R> {
+    pub fn new(renderer: R) -> Screen<R> {
+        Screen { renderer }
+    }
+}
+
+impl<R: Renderer> Subscriber for Screen<R> {
+    fn notify(&mut self, event: &Event, game: &Game) {
+        match event {
+            Event::Warped { .. } | Event::Resized => self.renderer.invalidate(),
+            Event::Changed => self.renderer.draw(&game.lines(self.renderer.size())),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::with_color;
+
+    fn lines(input: &[&str]) -> Vec<String> {
+        input.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn it_should_keep_colours_with_their_cells() {
+        let frame = Frame::new(&[format!("a{}b", with_color("░░", color::Yellow))]);
+        let yellow = color::Fg(color::Yellow).to_string();
+
+        assert_eq!(frame.get(0, 0).style, "");
+        assert_eq!(frame.get(1, 0).style, yellow);
+        assert_eq!(frame.get(2, 0).style, yellow);
+        assert_eq!(frame.get(3, 0).style, "");
+        assert_eq!(frame.get(3, 0).glyph, 'b');
+    }
+
+    #[test]
+    fn it_should_only_draw_changed_cells() {
+        let mut renderer = Terminal::default();
+        let first = renderer.update(Frame::new(&lines(&["█ →█", "████"])));
+        assert!(first.starts_with(&clear::All.to_string()));
+
+        let second = renderer.update(Frame::new(&lines(&["█→ █", "████"])));
+        assert_eq!(second, format!("{}→ ", cursor::Goto(2, 1)));
+
+        let third = renderer.update(Frame::new(&lines(&["█→ █"])));
+        assert_eq!(third, format!("{}    ", cursor::Goto(1, 2)));
+    }
+
+    #[test]
+    fn it_should_keep_what_was_drawn_into_a_buffer() {
+        let buffer = Buffer::new((4, 2));
+        let mut renderer: Box<dyn Renderer> = Box::new(buffer.clone());
+        renderer.draw(&[format!("a{}", with_color("░", color::Yellow))]);
+
+        assert_eq!(renderer.size(), (4, 2));
+        assert_eq!(buffer.frame().plain(), vec!["a░"]);
+        assert_eq!(
+            buffer.frame().ansi(),
+            vec![format!("a{}", with_color("░", color::Yellow))]
+        );
+    }
+
+    #[test]
+    fn it_should_redraw_everything_when_invalidated() {
+        let mut renderer = Terminal::default();
+        renderer.update(Frame::new(&lines(&["ab"])));
+        renderer.invalidate();
+
+        let out = renderer.update(Frame::new(&lines(&["ab"])));
+        assert!(out.starts_with(&clear::All.to_string()));
+    }
+}
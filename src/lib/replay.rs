@@ -0,0 +1,310 @@
+use crate::{
+    events::Command,
+    game::Game,
+    input::{Action, Movement},
+    pathfinding::Neighbours,
+};
+use std::{
+    fmt::{self, Display},
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+pub const VERSION: u32 = 1;
+
+const HEADER: &str = "terminal-madness replay";
+
+///
+/// Everything a game needs to play out the same way again: how it was set up,
+/// which maps it was played on, the save it could continue from, and every
+/// command with the tick it was handled on.
+///
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Recording {
+    pub seed: u64,
+    pub maps: u64,
+    pub movement: Movement,
+    pub neighbours: Neighbours,
+    pub save: Option<String>,
+    pub commands: Vec<(u64, Command)>,
+}
+
+///
+/// FNV-1a over the map source, which unlike the std hasher stays the same
+/// from one build to the next.
+///
+pub fn hash_maps(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn command_line(tick: u64, command: &Command) -> String {
+    match command {
+        Command::Act(action) => format!("{} {}", tick, action.name()),
+        Command::Resize => format!("{} resize", tick),
+    }
+}
+
+///
+/// A header with the format version, the setup as `key value` lines and then
+/// one `tick command` line per command, e.g.
+///
+///     terminal-madness replay 1
+///     seed 42
+///     maps 8c4b0e3a51f2d7c9
+///     movement step
+///     diagonal
+///     save terminal-madness 1
+///     save map 0,0
+///     0 confirm
+///     31 up
+///     40 resize
+///
+impl Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "maps {:016x}", self.maps)?;
+        writeln!(f, "movement {}", self.movement.name())?;
+        if self.neighbours == Neighbours::Eight {
+            writeln!(f, "diagonal")?;
+        }
+        for line in self.save.iter().flat_map(|save| save.lines()) {
+            writeln!(f, "save {}", line)?;
+        }
+        for (tick, command) in &self.commands {
+            writeln!(f, "{}", command_line(*tick, command))?;
+        }
+        Ok(())
+    }
+}
+
+impl Recording {
+    pub fn parse(str: &str) -> Result<Recording, String> {
+        let mut lines = str.lines().enumerate();
+        match lines.next() {
+            Some((_, line)) if line.trim() == format!("{} {}", HEADER, VERSION) => {}
+            _ => return Err("not a replay file, or not one of this version".to_string()),
+        }
+
+        let mut recording = Recording::default();
+        let mut maps = None;
+        let mut save = Vec::new();
+        for (i, line) in lines {
+            let error = |message: String| Err(format!("line {}: {}", i + 1, message));
+            if let Some(line) = line.strip_prefix("save ") {
+                save.push(line);
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let (key, value) = match (fields.next(), fields.next()) {
+                (None, _) => continue,
+                (Some(key), value) => (key, value.unwrap_or("")),
+            };
+
+            match key {
+                "seed" => match value.parse() {
+                    Ok(seed) => recording.seed = seed,
+                    Err(_) => return error(format!("bad seed \"{}\"", value)),
+                },
+                "maps" => match u64::from_str_radix(value, 16) {
+                    Ok(hash) => maps = Some(hash),
+                    Err(_) => return error(format!("bad maps hash \"{}\"", value)),
+                },
+                "movement" => match Movement::from_name(value) {
+                    Some(movement) => recording.movement = movement,
+                    None => return error(format!("unknown movement \"{}\"", value)),
+                },
+                "diagonal" => recording.neighbours = Neighbours::Eight,
+                tick => {
+                    let tick = match tick.parse::<u64>() {
+                        Ok(tick) => tick,
+                        Err(_) => return error(format!("unexpected \"{}\"", line)),
+                    };
+                    if recording
+                        .commands
+                        .last()
+                        .is_some_and(|(last, _)| *last > tick)
+                    {
+                        return error(format!("tick {} is before the one above", tick));
+                    }
+
+                    let command = match value {
+                        "resize" => Command::Resize,
+                        name => match Action::from_name(name) {
+                            Some(action) => Command::Act(action),
+                            None => return error(format!("unknown action \"{}\"", name)),
+                        },
+                    };
+                    recording.commands.push((tick, command));
+                }
+            }
+        }
+
+        match maps {
+            Some(hash) => recording.maps = hash,
+            None => return Err("no maps hash, cannot tell what it was played on".to_string()),
+        }
+        if !save.is_empty() {
+            recording.save = Some(save.iter().map(|line| format!("{}\n", line)).collect());
+        }
+
+        Ok(recording)
+    }
+
+    ///
+    /// Whether this was recorded on the maps in `text`, since the commands
+    /// mean nothing on any others.
+    ///
+    pub fn played_on(&self, text: &str) -> bool {
+        self.maps == hash_maps(text)
+    }
+
+    ///
+    /// Sets `game` up the way the recorded one was, with the save it started
+    /// from written to `save_path`, or nothing there if it had none.
+    ///
+    pub fn prepare(&self, game: &mut Game, save_path: PathBuf) -> io::Result<()> {
+        match &self.save {
+            Some(save) => fs::write(&save_path, save)?,
+            None if save_path.is_file() => fs::remove_file(&save_path)?,
+            None => {}
+        }
+
+        game.set_save_path(save_path);
+        game.set_movement(self.movement);
+        game.set_neighbours(self.neighbours);
+        game.set_seed(self.seed);
+        Ok(())
+    }
+
+    ///
+    /// Hands `game` every command on the tick it was recorded on, calling
+    /// `each` after every tick, until the commands run out or the game quits.
+    ///
+    pub fn play<F: FnMut(&mut Game)>(&self, game: &mut Game, mut each: F) {
+        let mut commands = self.commands.iter().peekable();
+
+        while game.is_running() {
+            while let Some((_, command)) = commands.next_if(|(tick, _)| *tick <= game.ticks()) {
+                game.handle(command);
+            }
+            if commands.peek().is_none() {
+                game.publish();
+                break;
+            }
+
+            game.tick();
+            each(game);
+        }
+    }
+}
+
+///
+/// Writes a recording as the game is played, a line at a time so that as much
+/// as possible is kept however the game ends.
+///
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    ///
+    /// Starts a recording at `path` with the setup in `recording`.
+    ///
+    pub fn create(path: &Path, recording: &Recording) -> io::Result<Recorder> {
+        let mut file = File::create(path)?;
+        write!(file, "{}", recording)?;
+        file.flush()?;
+        Ok(Recorder { file })
+    }
+
+    pub fn record(&mut self, tick: u64, command: &Command) -> io::Result<()> {
+        writeln!(self.file, "{}", command_line(tick, command))?;
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        helper::parse_maps,
+        render::{Buffer, Screen},
+        shared::Direction,
+        splash::Screens,
+    };
+    use std::{collections::HashMap, env};
+
+    fn input(action: Action) -> Command {
+        Command::Act(action)
+    }
+
+    fn recording() -> Recording {
+        Recording {
+            seed: 7,
+            maps: hash_maps(include_str!("../../lib/maps")),
+            movement: Movement::Step,
+            neighbours: Neighbours::Eight,
+            save: None,
+            commands: vec![
+                (0, input(Action::Confirm)),
+                (25, input(Action::Move(Direction::Up))),
+                (25, Command::Resize),
+                (70, input(Action::Turn(Direction::Left))),
+                (130, input(Action::Move(Direction::Up))),
+                (200, input(Action::Choose(2))),
+            ],
+        }
+    }
+
+    #[test]
+    fn it_should_round_trip_a_recording() {
+        let mut recording = recording();
+        recording.save = Some("terminal-madness 1\nmap 0,1\n".to_string());
+
+        let str = recording.to_string();
+        assert!(str.contains("\n25 up\n25 resize\n70 turn-left\n"));
+        assert_eq!(Recording::parse(&str), Ok(recording));
+
+        assert!(Recording::parse("seed 1").is_err());
+        assert_eq!(
+            Recording::parse("terminal-madness replay 1\nmaps 1f\n9 up\n3 up"),
+            Err("line 4: tick 3 is before the one above".to_string())
+        );
+        assert!(Recording::parse("terminal-madness replay 1\n9 up").is_err());
+    }
+
+    #[test]
+    fn it_should_only_play_on_the_maps_it_was_recorded_on() {
+        let maps = include_str!("../../lib/maps");
+        let recording = Recording::parse(&recording().to_string()).unwrap();
+
+        assert!(recording.played_on(maps));
+        assert!(!recording.played_on(&format!("{}\n", maps)));
+        assert_ne!(hash_maps(""), hash_maps(" "));
+    }
+
+    #[test]
+    fn it_should_play_out_the_same_every_time() {
+        let play = || {
+            let maps = parse_maps(include_str!("../../lib/maps")).unwrap();
+            let mut game = Game::new(maps, HashMap::new(), HashMap::new(), Screens::default());
+            let path = env::temp_dir().join("terminal-madness-replay-test.save");
+            game.subscribe(Box::new(Screen::new(Buffer::default())));
+            recording().prepare(&mut game, path).unwrap();
+
+            let mut npc = Vec::new();
+            recording().play(&mut game, |game| npc.push(game.to_save().npc));
+            (npc, game.to_save())
+        };
+
+        let (npc, save) = play();
+        assert_eq!(npc.len(), 200);
+        assert_eq!(save.player, (2, 5, 0));
+        assert_eq!(play(), (npc, save));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    helper::{unwrap_and_parse, unwrap_or},
+    shared::Point,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    fmt::{self, Display},
+    path::PathBuf,
+    str::FromStr,
+};
+
+pub const VERSION: u32 = 1;
+
+const HEADER: &str = "terminal-madness";
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Save {
+    pub map: (u8, u8),
+    pub player: (usize, usize, u8),
+    pub entries: BTreeMap<(u8, u8), Point>,
+    pub npc: BTreeMap<u8, Point>,
+    pub flags: BTreeSet<String>,
+}
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum SaveError {
+    Header,
+    Version(u32),
+    Line(usize, String),
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Header => write!(f, "not a save file"),
+            SaveError::Version(version) => write!(f, "unsupported save version {}", version),
+            SaveError::Line(line, text) => write!(f, "line {}: unexpected \"{}\"", line, text),
+        }
+    }
+}
+
+pub fn save_path() -> PathBuf {
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".terminal-madness.save"),
+        None => PathBuf::from("terminal-madness.save"),
+    }
+}
+
+///
+/// Exactly `count` comma separated numbers, or nothing if any is missing,
+/// extra or not a number.
+///
+fn numbers<T: FromStr>(input: &str, count: usize) -> Option<Vec<T>> {
+    let values = input
+        .split(',')
+        .map(|value| value.parse().ok())
+        .collect::<Option<Vec<T>>>()?;
+
+    Some(values).filter(|values| values.len() == count)
+}
+
+fn point(input: &str) -> Option<Point> {
+    numbers(input, 2).map(|xy| Point { x: xy[0], y: xy[1] })
+}
+
+///
+/// A header with the format version followed by one `key value` pair per
+/// line, e.g.
+///
+///     terminal-madness 1
+///     map 0,1
+///     player 17,7,0
+///     entry 0,0 15,1
+///     npc 0 22,5
+///     flag gate
+///
+impl Display for Save {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        writeln!(f, "map {},{}", self.map.0, self.map.1)?;
+        writeln!(
+            f,
+            "player {},{},{}",
+            self.player.0, self.player.1, self.player.2
+        )?;
+        for ((area, part), point) in &self.entries {
+            writeln!(f, "entry {},{} {},{}", area, part, point.x, point.y)?;
+        }
+        for (id, point) in &self.npc {
+            writeln!(f, "npc {} {},{}", id, point.x, point.y)?;
+        }
+        for flag in &self.flags {
+            writeln!(f, "flag {}", flag)?;
+        }
+        Ok(())
+    }
+}
+
+impl Save {
+    pub fn parse(str: &str) -> Result<Save, SaveError> {
+        let mut lines = str.lines().map(str::trim).enumerate();
+
+        let mut header = unwrap_or(&mut lines, (0, "")).1.split(' ');
+        if unwrap_or(&mut header, "") != HEADER {
+            return Err(SaveError::Header);
+        }
+
+        let version = unwrap_and_parse(&mut header, "0", 0);
+        if version != VERSION {
+            return Err(SaveError::Version(version));
+        }
+
+        let mut save = Save::default();
+        for (i, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let mut fields = line.split(' ');
+            let (key, a, b) = (
+                unwrap_or(&mut fields, ""),
+                unwrap_or(&mut fields, ""),
+                unwrap_or(&mut fields, ""),
+            );
+
+            let parsed = match (key, b.is_empty()) {
+                ("map", true) => numbers::<u8>(a, 2).map(|map| save.map = (map[0], map[1])),
+                ("player", true) => numbers::<usize>(a, 3).and_then(|player| {
+                    if player[2] > 3 {
+                        return None;
+                    }
+                    save.player = (player[0], player[1], player[2] as u8);
+                    Some(())
+                }),
+                ("entry", false) => numbers::<u8>(a, 2)
+                    .zip(point(b))
+                    .map(|(map, point)| save.entries.insert((map[0], map[1]), point))
+                    .map(|_| ()),
+                ("npc", false) => a
+                    .parse::<u8>()
+                    .ok()
+                    .zip(point(b))
+                    .map(|(id, point)| save.npc.insert(id, point))
+                    .map(|_| ()),
+                ("flag", true) if !a.is_empty() => {
+                    save.flags.insert(a.to_string());
+                    Some(())
+                }
+                _ => None,
+            };
+
+            if parsed.is_none() || fields.next().is_some() {
+                return Err(SaveError::Line(i + 1, line.to_string()));
+            }
+        }
+
+        Ok(save)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_round_trip_a_save() {
+        let mut save = Save {
+            map: (0, 1),
+            player: (17, 7, 0),
+            ..Save::default()
+        };
+        save.entries.insert((0, 0), Point { x: 15, y: 1 });
+        save.npc.insert(0, Point { x: 22, y: 5 });
+        save.flags.insert("gate".to_string());
+
+        let str = save.to_string();
+        assert!(str.starts_with("terminal-madness 1\n"));
+        assert_eq!(Save::parse(&str), Ok(save));
+    }
+
+    #[test]
+    fn it_should_reject_other_versions_and_unknown_lines() {
+        assert_eq!(
+            Save::parse("terminal-madness 2\nmap 0,0"),
+            Err(SaveError::Version(2))
+        );
+        assert_eq!(Save::parse("map 0,0"), Err(SaveError::Header));
+        assert_eq!(
+            Save::parse("terminal-madness 1\nmap 0,0\nhat on"),
+            Err(SaveError::Line(3, "hat on".to_string()))
+        );
+    }
+
+    #[test]
+    fn it_should_reject_numbers_that_do_not_parse() {
+        for line in &[
+            "npc x 22,5",
+            "npc 0 22",
+            "player 17,7",
+            "player 1,1,9",
+            "map 0,1,2",
+        ] {
+            assert_eq!(
+                Save::parse(&format!("terminal-madness 1\n{}", line)),
+                Err(SaveError::Line(2, line.to_string()))
+            );
+        }
+    }
+}
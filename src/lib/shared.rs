@@ -6,6 +6,7 @@ use std::{
     ops::RangeInclusive,
 };
 
+#[derive(Clone, Eq, PartialEq, Debug)]
 pub struct ParsedMap {
     pub area: u8,
     pub part: u8,
@@ -53,7 +54,7 @@ impl PartialOrd for Point {
     }
 }
 
-pub fn rand_range(input: &RangeInclusive<u8>) -> u8 {
+pub fn rand_range<R: Rng>(rng: &mut R, input: &RangeInclusive<u8>) -> u8 {
     let mut a = input.start();
     let mut b = input.end();
 
@@ -61,9 +62,10 @@ pub fn rand_range(input: &RangeInclusive<u8>) -> u8 {
         mem::swap(&mut a, &mut b);
     }
 
-    rand::thread_rng().gen_range(a, b + 1)
+    rng.gen_range(*a as u16, *b as u16 + 1) as u8
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub enum Tile {
     Wall,
@@ -71,10 +73,13 @@ pub enum Tile {
     Current,
     Warp,
     NPC,
+    Grass,
+    Mud,
     Unknown,
 }
 
 impl Tile {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(input: &str) -> Tile {
         match input {
             "0" => Tile::Wall,
@@ -82,10 +87,35 @@ impl Tile {
             "2" => Tile::Current,
             "3" => Tile::Warp,
             "4" => Tile::NPC,
+            "5" => Tile::Grass,
+            "6" => Tile::Mud,
             _ => Tile::Unknown,
         }
     }
 
+    ///
+    /// Ground that can be stood on when nobody else is, plain floor or one
+    /// of the slower terrains.
+    ///
+    pub fn is_floor(&self) -> bool {
+        matches!(self, Tile::Empty | Tile::Grass | Tile::Mud)
+    }
+
+    ///
+    /// What it costs to step onto the tile, `None` for what can't be walked
+    /// through. Characters count as floor, they will have moved on by the
+    /// time anyone gets there. Warps only take the player elsewhere, npcs
+    /// never step on them.
+    ///
+    pub fn cost(&self) -> Option<usize> {
+        match self {
+            Tile::Empty | Tile::Current | Tile::NPC => Some(1),
+            Tile::Grass => Some(2),
+            Tile::Mud => Some(4),
+            Tile::Wall | Tile::Warp | Tile::Unknown => None,
+        }
+    }
+
     pub fn from_u8(input: &u8) -> Tile {
         Tile::from_str(&input.to_string())
     }
@@ -114,7 +144,7 @@ impl Direction {
             0 => Direction::Up,
             1 => Direction::Down,
             2 => Direction::Right,
-            3 | _ => Direction::Left,
+            _ => Direction::Left,
         }
     }
 }
@@ -122,13 +152,28 @@ impl Direction {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
 
     #[test]
     fn it_should_render_expected_random_numbers() {
         let range = 0..=10;
+        let mut rng = StdRng::seed_from_u64(0);
         for _ in 0..1000 {
-            let rand = rand_range(&range);
+            let rand = rand_range(&mut rng, &range);
             assert!(range.contains(&rand));
         }
     }
+
+    #[test]
+    fn it_should_repeat_random_numbers_for_a_seed() {
+        let numbers = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..10)
+                .map(|_| rand_range(&mut rng, &(0..=255)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(numbers(7), numbers(7));
+        assert_ne!(numbers(7), numbers(8));
+    }
 }
@@ -0,0 +1,128 @@
+use crate::helper::{unwrap_or, with_color};
+use std::collections::HashMap;
+use termion::color;
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct Screens {
+    pub title: Vec<String>,
+    pub cards: HashMap<u8, Vec<String>>,
+    pub names: HashMap<(u8, u8), String>,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum MenuItem {
+    NewGame,
+    Continue,
+    Quit,
+}
+
+pub const MENU: [MenuItem; 3] = [MenuItem::NewGame, MenuItem::Continue, MenuItem::Quit];
+
+impl MenuItem {
+    pub fn label(&self) -> &str {
+        match self {
+            MenuItem::NewGame => "New game",
+            MenuItem::Continue => "Continue",
+            MenuItem::Quit => "Quit",
+        }
+    }
+}
+
+///
+/// One line per row, `key|text`, where `key` is `title` for the title screen,
+/// the area a card is shown for when the player warps into it, or
+/// `area,part` for the name of a map.
+///
+pub fn parse_screens(str: &str) -> Screens {
+    let mut screens = Screens::default();
+
+    for line in str.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut fields = line.splitn(2, '|');
+        let key = unwrap_or(&mut fields, "").trim();
+        let text = unwrap_or(&mut fields, "").trim_end().to_string();
+
+        match key {
+            "title" => screens.title.push(text),
+            _ => {
+                if let Ok(area) = key.parse::<u8>() {
+                    screens.cards.entry(area).or_default().push(text);
+                    continue;
+                }
+
+                let mut key = key.splitn(2, ',').map(|value| value.trim().parse::<u8>());
+                if let (Some(Ok(area)), Some(Ok(part))) = (key.next(), key.next()) {
+                    screens.names.insert((area, part), text.trim().to_string());
+                }
+            }
+        }
+    }
+
+    screens
+}
+
+impl Screens {
+    ///
+    /// The name of a map, or failing that the first line of its area card.
+    ///
+    pub fn name(&self, map: (u8, u8)) -> String {
+        match (self.names.get(&map), self.cards.get(&map.0)) {
+            (Some(name), _) => name.to_owned(),
+            (None, Some(card)) if !card.is_empty() => card[0].to_owned(),
+            _ => format!("area {}, part {}", map.0, map.1),
+        }
+    }
+}
+
+pub fn title_screen(title: &[String], selected: usize, can_continue: bool) -> Vec<String> {
+    let mut out = title.to_vec();
+    out.push(String::new());
+
+    for (i, item) in MENU.iter().enumerate() {
+        let marker = if i == selected { ">" } else { " " };
+        let line = format!("  {} {}", marker, item.label());
+        out.push(if item == &MenuItem::Continue && !can_continue {
+            with_color(&line, color::LightBlack)
+        } else {
+            line
+        });
+    }
+
+    out
+}
+
+pub fn card(lines: &[String]) -> Vec<String> {
+    let width = lines.iter().map(|line| line.chars().count()).max();
+    let width = width.unwrap_or(0).max(7);
+    let pad = |line: &str| {
+        let len = line.chars().count();
+        format!("║ {}{} ║", line, " ".repeat(width - len))
+    };
+
+    let mut out = vec![format!("╔{}╗", "═".repeat(width + 2))];
+    for line in lines {
+        out.push(pad(line));
+    }
+    out.push(pad(""));
+    out.push(pad(&format!("{:>1$}", "[enter]", width)));
+    out.push(format!("╚{}╝", "═".repeat(width + 2)));
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_name_maps_and_fall_back_to_the_area_card() {
+        let screens = parse_screens("title|x\n0|The Cellar\n0|\n0,1|Back room");
+
+        assert_eq!(screens.name((0, 1)), "Back room");
+        assert_eq!(screens.name((0, 0)), "The Cellar");
+        assert_eq!(screens.name((2, 0)), "area 2, part 0");
+    }
+}
@@ -0,0 +1,310 @@
+use crate::{
+    helper::{Block, Field, MapErrorKind, Parser, Token},
+    shared::{ParsedMap, Point},
+};
+use std::collections::HashMap;
+
+pub const GLYPH_WALL: char = '█';
+pub const GLYPH_EMPTY: char = ' ';
+pub const GLYPH_WARP: char = '░';
+pub const GLYPH_NPC: char = 'X';
+pub const GLYPH_GRASS: char = '"';
+pub const GLYPH_MUD: char = '~';
+
+pub fn is_visual(line: &str) -> bool {
+    line.trim_start().starts_with("map ")
+}
+
+fn words<'a>(token: &Token<'a>) -> Vec<Token<'a>> {
+    token
+        .split(' ')
+        .into_iter()
+        .filter(|word| !word.text.is_empty())
+        .collect()
+}
+
+fn pair<T: std::str::FromStr>(
+    parser: &mut Parser,
+    token: &Token,
+    names: &[&'static str],
+) -> Option<(T, T)> {
+    let values = parser.values(token, ',', names, 2)?;
+    let (a, b) = (parser.number(&values[0]), parser.number(&values[1]));
+    Some((a?, b?))
+}
+
+type Props = Vec<(u8, (u8, u8), (u8, u8), usize)>;
+
+#[derive(Default)]
+struct Header {
+    key: Option<(u8, u8)>,
+    max: Option<(usize, usize)>,
+    player: Option<((usize, usize, u8), usize, usize)>,
+    warps: HashMap<Point, ((u8, u8), usize, usize)>,
+    npc: HashMap<Point, ((u8, u8), usize, usize)>,
+}
+
+fn header_line(parser: &mut Parser, words: &[Token], header: &mut Header, props: &mut Props) {
+    let (key, args) = (words[0], &words[1..]);
+    let expect = |parser: &mut Parser, count: usize, names: &[&'static str]| {
+        if args.len() < count {
+            parser.error(key.end(), MapErrorKind::Missing(names[args.len()]));
+            return false;
+        }
+        if let Some(extra) = args.get(count) {
+            parser.error(extra.column, MapErrorKind::Unexpected(extra.text.into()));
+            return false;
+        }
+        true
+    };
+
+    match key.text {
+        "map" if expect(parser, 1, &["area,part"]) => {
+            header.key = pair(parser, &args[0], &["area", "part"]);
+        }
+        "size" if expect(parser, 1, &["width,height"]) => {
+            header.max = pair(parser, &args[0], &["width", "height"]);
+        }
+        "player" if expect(parser, 1, &["x,y,direction"]) => {
+            let values = match parser.values(&args[0], ',', &["x", "y", "direction"], 3) {
+                Some(values) => values,
+                None => return,
+            };
+            let (x, y, direction) = (
+                parser.number(&values[0]),
+                parser.number(&values[1]),
+                parser.number::<u8>(&values[2]),
+            );
+            if let Some(direction) = direction.filter(|direction| *direction > 3) {
+                parser.error(values[2].column, MapErrorKind::Direction(direction));
+                return;
+            }
+            if let (Some(x), Some(y), Some(direction)) = (x, y, direction) {
+                header.player = Some(((x, y, direction), parser.line, args[0].column));
+            }
+        }
+        "props" if expect(parser, 2, &["id", "x-x,y-y"]) => {
+            let id = parser.number(&args[0]);
+            let ranges = match parser.values(&args[1], ',', &["x", "y"], 2) {
+                Some(ranges) => ranges,
+                None => return,
+            };
+            let (x, y) = (parser.range(&ranges[0]), parser.range(&ranges[1]));
+            if let (Some(id), Some(x), Some(y)) = (id, x, y) {
+                props.push((id, x, y, key.column));
+            }
+        }
+        "warp" | "npc" if expect(parser, 2, &["x,y", "meta"]) => {
+            let point = pair(parser, &args[0], &["x", "y"]);
+            let meta = pair(parser, &args[1], &["meta", "meta"]);
+            if let (Some((x, y)), Some(meta)) = (point, meta) {
+                let entry = (meta, parser.line, key.column);
+                match key.text {
+                    "warp" => header.warps.insert(Point { x, y }, entry),
+                    _ => header.npc.insert(Point { x, y }, entry),
+                };
+            }
+        }
+        "map" | "size" | "player" | "props" | "warp" | "npc" => {}
+        _ => parser.error(key.column, MapErrorKind::Unexpected(key.text.into())),
+    }
+}
+
+///
+/// Parses one map drawn with the glyphs `Map::grid_lines` uses, from its
+/// `map` line up to, not including, the `end` line:
+///
+///     map 0,1
+///     size 24,9
+///     player 17,7,0
+///     props 0 11-23,4-7
+///     warp 17,8 0,0
+///     grid
+///               ██████████████
+///               █            █
+///               ██████░███████
+///     legend
+///     npc 22,5 0,0
+///
+/// `warp` gives the target of a `░` in the grid and `npc` the meta of an `X`,
+/// both may be given in the header or the legend. Missing trailing spaces and
+/// rows are empty floor.
+///
+pub fn parse_visual(parser: &mut Parser, lines: &[(usize, &str)], closed: bool) -> Option<Block> {
+    let mut header = Header::default();
+    let mut props = Vec::new();
+    let mut rows = Vec::new();
+    let mut field = Field::Header;
+
+    for (number, line) in lines {
+        parser.line = *number;
+        let token = Token::line(line);
+
+        if field == Field::Grid {
+            match token.text {
+                "legend" => field = Field::Legend,
+                _ => rows.push((*number, line.trim_end())),
+            }
+            continue;
+        }
+
+        parser.field = field;
+        let words = words(&token);
+        match words.first().map(|word| word.text) {
+            None => {}
+            Some("grid") if field == Field::Header => field = Field::Grid,
+            Some(_) => header_line(parser, &words, &mut header, &mut props),
+        }
+    }
+
+    let (number, first) = lines[0];
+    parser.line = number;
+    parser.field = Field::Header;
+
+    if !closed {
+        let (number, last) = lines[lines.len() - 1];
+        parser.line = number;
+        parser.error(Token::line(last).end(), MapErrorKind::Missing("end"));
+        return None;
+    }
+
+    let column = Token::line(first).column;
+    let (key, max, player) = match (header.key, header.max, header.player) {
+        (None, _, _) => return None,
+        (_, None, _) => {
+            parser.error(column, MapErrorKind::Missing("size"));
+            return None;
+        }
+        (_, _, None) => {
+            parser.error(column, MapErrorKind::Missing("player"));
+            return None;
+        }
+        (Some(key), Some(max), Some(player)) => (key, max, player),
+    };
+
+    let mut map = ParsedMap {
+        area: key.0,
+        part: key.1,
+        grid: HashMap::new(),
+        max,
+        player: player.0,
+        props: HashMap::new(),
+    };
+
+    parser.field = Field::Props;
+    for (id, x, y, column) in props {
+        if x.0.max(x.1) as usize >= max.0 || y.0.max(y.1) as usize >= max.1 {
+            parser.error(column, MapErrorKind::PropsOutside(id));
+        }
+        map.props.insert(id, (x.0..=x.1, y.0..=y.1));
+    }
+
+    parser.field = Field::Grid;
+    let mut warps = Vec::new();
+    for (y, (number, row)) in rows.iter().enumerate() {
+        parser.line = *number;
+
+        for (x, glyph) in row.chars().enumerate() {
+            let point = Point { x, y };
+            if glyph != GLYPH_EMPTY && (x >= max.0 || y >= max.1) {
+                parser.error(x + 1, MapErrorKind::OutOfBounds(point));
+                break;
+            }
+
+            let tile = match glyph {
+                GLYPH_EMPTY => continue,
+                GLYPH_WALL => (0, (0, 0)),
+                GLYPH_GRASS => (5, (0, 0)),
+                GLYPH_MUD => (6, (0, 0)),
+                GLYPH_WARP | GLYPH_NPC => {
+                    let entries = match glyph {
+                        GLYPH_WARP => &mut header.warps,
+                        _ => &mut header.npc,
+                    };
+                    match entries.remove(&point) {
+                        Some((meta, line, column)) => {
+                            if glyph == GLYPH_WARP {
+                                warps.push((line, column, meta));
+                            }
+                            (if glyph == GLYPH_WARP { 3 } else { 4 }, meta)
+                        }
+                        None => {
+                            parser.error(x + 1, MapErrorKind::NoLegend(point));
+                            continue;
+                        }
+                    }
+                }
+                _ => {
+                    parser.error(x + 1, MapErrorKind::Unexpected(glyph.to_string()));
+                    continue;
+                }
+            };
+
+            map.grid.insert(point, tile);
+        }
+    }
+
+    parser.field = Field::Legend;
+    for (point, (_, line, column)) in header.warps.iter().chain(header.npc.iter()) {
+        parser.line = *line;
+        parser.error(*column, MapErrorKind::NoGlyph(point.to_owned()));
+    }
+
+    let last = lines
+        .last()
+        .map_or(number, |(last, _)| last + closed as usize);
+
+    Some(Block {
+        map,
+        lines: (number, last),
+        header: (number, column),
+        player: (player.1, player.2),
+        warps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helper::{parse_maps, MapErrorKind};
+    use crate::shared::Point;
+
+    const VISUAL: &str = "
+map 0,1
+size 8,4
+player 2,1,0
+props 0 1-6,1-2
+warp 3,3 0,1
+grid
+████████
+█      █
+█  X   █
+███░████
+legend
+npc 3,2 0,0
+end
+";
+
+    #[test]
+    fn it_should_parse_the_same_map_as_the_compact_format() {
+        let compact = "0|1|8,4|2,1,0\n0,1-6,1-2\n0-7,0|0,1-2|7,1-2|0-2,3|4-7,3|3,3,3,0,1|3,2,4,0,0";
+
+        assert_eq!(parse_maps(VISUAL), parse_maps(compact));
+        assert!(parse_maps(VISUAL).is_ok());
+    }
+
+    #[test]
+    fn it_should_mix_with_compact_maps() {
+        let mixed = format!("{}\n0|0|3,1|0,0,0\n2,0,3,0,1", VISUAL);
+        assert_eq!(parse_maps(&mixed).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn it_should_require_legend_entries_for_warps_and_npcs() {
+        let error = parse_maps(&VISUAL.replace("npc 3,2 0,0\n", "npc 4,2 0,0\n"))
+            .err()
+            .unwrap();
+
+        assert_eq!(error.line, 10);
+        assert_eq!(error.kind, MapErrorKind::NoLegend(Point { x: 3, y: 2 }));
+    }
+}
@@ -1,124 +1,381 @@
-#[macro_use]
-extern crate lazy_static;
 extern crate rand;
 
-mod lib;
-
-use lib::{game::Game, helper::parse_maps, shared::Point};
-use rand::Rng;
 use std::{
-    cmp::max,
-    io::{stdout, Read},
-    sync::{mpsc, Arc, Mutex},
+    env, fs,
+    io::{self, stdout, Read},
+    path::Path,
+    process,
+    sync::mpsc,
     thread,
     time::{Duration, Instant},
 };
+use terminal_madness::{
+    camera::screen_size,
+    check,
+    cli::{parse_args, Mode, USAGE},
+    dialogue::parse_dialogues,
+    editor::Editor,
+    events::{Bell, Command},
+    export::export_maps,
+    files::{default_maps_path, maps_source, Source},
+    flags::parse_triggers,
+    game::{Game, TICK},
+    helper::parse_maps,
+    input::{keys_path, Action, Decoder, Key, Keymap},
+    render::{Buffer, Screen, Terminal},
+    replay::{hash_maps, Recorder, Recording},
+    save::save_path,
+    shared::Direction,
+    splash::parse_screens,
+};
 use termion::{async_stdin, clear, cursor, raw::IntoRawMode};
 
-enum Message {
-    MoveNPC { meta: (u8, u8, u8), point: Point },
-    MovePlayer { key: u8 },
-    Interact,
-}
-
 fn main() {
-    let (tx, rx) = mpsc::channel();
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
 
-    let maps = parse_maps(&include_str!("../lib/maps"));
-    let game = Arc::new(Mutex::new(Game::new(maps)));
+    if args.help {
+        println!("{}", USAGE);
+        return;
+    }
 
-    let mut stdin = async_stdin().bytes();
-    let _stdout = stdout().into_raw_mode().unwrap();
+    let paths = if args.maps.is_empty() {
+        default_maps_path().into_iter().collect()
+    } else {
+        args.maps.to_owned()
+    };
+
+    let source = match maps_source(&paths) {
+        Ok(Some(source)) => source,
+        Ok(None) => Source::embedded(Path::new("lib/maps"), include_str!("../lib/maps")),
+        Err(error) => {
+            eprintln!("could not read maps: {}", error);
+            process::exit(1);
+        }
+    };
 
-    game.lock().unwrap().map.print_grid();
+    if args.mode == Mode::Check {
+        process::exit(check::run(&source));
+    }
 
-    let pathfinding = game.lock().unwrap().pathfinding.clone();
-    let in_thread_tx = tx.clone();
+    if let Mode::Edit(area, part) = args.mode {
+        match Editor::new(&source, (area, part)) {
+            Ok(editor) => edit(editor),
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+        return;
+    }
 
-    thread::spawn(move || loop {
-        let start = Instant::now();
-        let mut movement = Vec::new();
-        if let Ok(guard) = pathfinding.write() {
-            let mut guard = guard;
-            for (meta, points) in guard.iter_mut() {
-                if points.is_empty() {
-                    continue;
-                }
+    let keys = match &args.keys {
+        Some(path) => Some(path.to_owned()),
+        None => Some(keys_path()).filter(|path| path.is_file()),
+    };
+    let keymap = match keys.map(|path| (fs::read_to_string(&path), path)) {
+        None => Keymap::default(),
+        Some((Ok(text), path)) => match Keymap::parse(&text) {
+            Ok(keymap) => keymap,
+            Err(error) => {
+                eprintln!("{}: {}", path.display(), error);
+                process::exit(1);
+            }
+        },
+        Some((Err(error), path)) => {
+            eprintln!("could not read {}: {}", path.display(), error);
+            process::exit(1);
+        }
+    };
+
+    let maps = match parse_maps(&source.text) {
+        Ok(maps) => maps,
+        Err(error) => {
+            eprintln!("{}", source.describe(&error));
+            process::exit(1);
+        }
+    };
+
+    if let Mode::Export(format) = args.mode {
+        print!("{}", export_maps(&maps, format));
+        return;
+    }
 
-                let point = points.pop();
+    let dialogues = parse_dialogues(include_str!("../lib/dialogue"));
+    // the triggers are written for the maps built in, whichever are played
+    let bundled = parse_maps(include_str!("../lib/maps")).unwrap_or_default();
+    let triggers = match parse_triggers(include_str!("../lib/flags"), &bundled) {
+        Ok(triggers) => triggers,
+        Err(error) => {
+            let flags = Source::embedded(Path::new("lib/flags"), include_str!("../lib/flags"));
+            eprintln!("{}", flags.describe(&error));
+            process::exit(1);
+        }
+    };
+    let screens = parse_screens(include_str!("../lib/splash"));
+    let mut game = Game::new(maps, dialogues, triggers, screens);
+    let buffer = Buffer::default();
+    if args.headless {
+        game.subscribe(Box::new(Screen::new(buffer.clone())));
+    }
+
+    let replay = match &args.replay {
+        Some(path) => match fs::read_to_string(path).map(|text| Recording::parse(&text)) {
+            Ok(Ok(recording)) if recording.played_on(&source.text) => Some(recording),
+            Ok(Ok(_)) => {
+                eprintln!(
+                    "{}: recorded on other maps than these, refusing to replay",
+                    path.display()
+                );
+                process::exit(1);
+            }
+            Ok(Err(error)) => {
+                eprintln!("{}: {}", path.display(), error);
+                process::exit(1);
+            }
+            Err(error) => {
+                eprintln!("could not read {}: {}", path.display(), error);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let mut recorder = None;
+    match &replay {
+        Some(recording) => {
+            // never touch the real save, the replay gets its own copy
+            let path = env::temp_dir().join("terminal-madness-replay.save");
+            if let Err(error) = recording.prepare(&mut game, path) {
+                eprintln!("could not prepare the replay: {}", error);
+                process::exit(1);
+            }
+        }
+        None => {
+            // nor does a game played without a terminal, it starts afresh
+            let path = match args.headless {
+                true => env::temp_dir().join("terminal-madness-headless.save"),
+                false => save_path(),
+            };
+            if args.headless && path.is_file() {
+                let _ = fs::remove_file(&path);
+            }
+
+            let recording = Recording {
+                seed: args.seed.unwrap_or_else(rand::random),
+                maps: hash_maps(&source.text),
+                movement: args.movement,
+                neighbours: args.neighbours,
+                save: fs::read_to_string(&path).ok(),
+                commands: Vec::new(),
+            };
+            game.set_save_path(path);
+            game.set_movement(recording.movement);
+            game.set_neighbours(recording.neighbours);
+            game.set_seed(recording.seed);
 
-                if point.is_none() {
-                    continue;
+            if let Some(path) = &args.record {
+                match Recorder::create(path, &recording) {
+                    Ok(created) => recorder = Some(created),
+                    Err(error) => {
+                        eprintln!("could not record to {}: {}", path.display(), error);
+                        process::exit(1);
+                    }
                 }
+            }
+        }
+    }
 
-                let point = point.unwrap();
-                movement.push((meta.to_owned(), point.to_owned()));
+    if args.headless {
+        match &replay {
+            Some(recording) => recording.play(&mut game, |_| {}),
+            None => {
+                let mut script = String::new();
+                let played = io::stdin()
+                    .read_to_string(&mut script)
+                    .map_err(|error| error.to_string())
+                    .and_then(|_| play_script(&mut game, &script));
+                if let Err(error) = played {
+                    eprintln!("could not play the script: {}", error);
+                    process::exit(1);
+                }
             }
-        };
+        }
+
+        for line in buffer.frame().plain() {
+            println!("{}", line.trim_end());
+        }
+        print!("\nticks {}\n{}", game.ticks(), game.to_save());
+        return;
+    }
+
+    game.subscribe(Box::new(Screen::new(Terminal::default())));
+    if args.bell {
+        game.subscribe(Box::new(Bell));
+    }
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || read_input(keymap, tx));
+
+    let _stdout = stdout().into_raw_mode().unwrap();
+    game.redraw();
+
+    let mut next = Instant::now();
+    let mut pace = || {
+        next += TICK;
+        thread::sleep(next.saturating_duration_since(Instant::now()));
+    };
+
+    // while watching a replay only quitting is up to the keyboard, after it
+    // the game is played as usual
+    if let Some(recording) = &replay {
+        recording.play(&mut game, |game| {
+            let quit = Command::Act(Action::Quit);
+            if rx.try_iter().any(|command| command == quit) {
+                game.handle(&quit);
+            }
+            pace();
+        });
+    }
+
+    // one tick at a time, whatever came in since the last one first
+    let mut failed = None;
+    while game.is_running() {
+        for command in rx.try_iter() {
+            if let Some(Err(error)) = recorder
+                .as_mut()
+                .map(|recorder| recorder.record(game.ticks(), &command))
+            {
+                failed = Some(error);
+                recorder = None;
+            }
+            game.handle(&command);
+        }
+
+        game.tick();
+        pace();
+    }
 
-        for (meta, point) in movement {
-            let range = rand::thread_rng().gen_range(50, 250);
-            thread::sleep(Duration::from_millis(range as u64));
+    println!("{}{}{}", clear::All, cursor::Show, cursor::Goto(1, 1));
+    if let (Some(error), Some(path)) = (failed, &args.record) {
+        eprintln!("stopped recording to {}: {}", path.display(), error);
+    }
+}
 
-            in_thread_tx
-                .send(Message::MoveNPC {
-                    meta: meta.to_owned(),
-                    point: point.to_owned(),
-                })
-                .unwrap();
+///
+/// Plays one action name per line, as in key bindings, a tick apart;
+/// `wait <ticks>` lets time pass instead.
+///
+fn play_script(game: &mut Game, script: &str) -> Result<(), String> {
+    for (i, line) in script.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
         }
+        if !game.is_running() {
+            break;
+        }
+
+        let error = |message: String| Err(format!("line {}: {}", i + 1, message));
+        match line
+            .strip_prefix("wait ")
+            .map(|ticks| ticks.trim().parse::<u64>())
+        {
+            Some(Ok(ticks)) => game.wait(ticks),
+            Some(Err(_)) => return error(format!("bad wait \"{}\"", line)),
+            None => match Action::from_name(line) {
+                Some(action) => game.step(&action),
+                None => return error(format!("unknown action \"{}\"", line)),
+            },
+        };
+    }
+
+    Ok(())
+}
+
+///
+/// Reads keys and watches the size of the screen, sending what comes of it
+/// to the game loop; runs until the loop is gone.
+///
+fn read_input(keymap: Keymap, tx: mpsc::Sender<Command>) {
+    #[allow(clippy::unbuffered_bytes)]
+    let mut stdin = async_stdin().bytes();
+    let mut size = screen_size();
+    let mut decoder = Decoder::default();
 
-        let duration = start.elapsed().as_millis();
+    loop {
+        let mut commands = Vec::new();
 
-        let diff = if duration < 1000 { 1000 - duration } else { 0 };
+        // there is no SIGWINCH without another dependency, so poll instead
+        if screen_size() != size {
+            size = screen_size();
+            commands.push(Command::Resize);
+        }
 
-        thread::sleep(Duration::from_millis(diff as u64));
-    });
+        for key in read_keys(&mut stdin, &mut decoder) {
+            if let Some(action) = keymap.action(&key) {
+                commands.push(Command::Act(action));
+            }
+        }
 
-    thread::spawn(move || loop {
-        let msg = rx.recv().unwrap();
-        if let Ok(guard) = game.try_lock() {
-            let mut this = guard;
-            match msg {
-                Message::MoveNPC { meta, point } => this.move_npc(&meta, &point),
-                Message::MovePlayer { key } => this.move_player(&key),
-                Message::Interact => this.interact(),
+        for command in commands {
+            if tx.send(command).is_err() {
+                return;
             }
         }
-    });
 
-    let input_loop_tx = tx.clone();
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+///
+/// Everything typed since the last call, as keys; a sequence still coming in
+/// is left with the decoder until the next one.
+///
+fn read_keys<R: Iterator<Item = io::Result<u8>>>(stdin: &mut R, decoder: &mut Decoder) -> Vec<Key> {
+    let mut bytes = Vec::new();
+    while let Some(Ok(byte)) = stdin.next() {
+        bytes.push(byte);
+    }
+
+    match bytes.is_empty() {
+        true => decoder.idle(),
+        false => decoder.feed(&bytes),
+    }
+}
+
+fn edit(mut editor: Editor) {
+    #[allow(clippy::unbuffered_bytes)]
+    let mut stdin = async_stdin().bytes();
+    let _stdout = stdout().into_raw_mode().unwrap();
+
+    editor.render();
+    let mut size = screen_size();
+    let mut decoder = Decoder::default();
 
     'stdin: loop {
-        let start = Instant::now();
-        if let Some(Ok(val)) = stdin.next() {
-            match val {
-                // arrow sequence = 27+91+(65-68)
-                27 => {
-                    if let Some(Ok(val)) = stdin.next() {
-                        if val == 91 {
-                            input_loop_tx
-                                .send(Message::MovePlayer {
-                                    key: stdin.next().unwrap_or(Ok(0)).unwrap_or(0),
-                                })
-                                .unwrap();
-                            continue 'stdin;
-                        }
-                    }
-                }
-                // ctrl+c
-                3 => break 'stdin,
-                // enter
-                13 => print!("enter\r\n"),
-                // space
-                32 => input_loop_tx.send(Message::Interact).unwrap(),
-                _ => {}
-            }
+        if screen_size() != size {
+            size = screen_size();
+            editor.redraw();
         }
 
-        let duration = start.elapsed().as_millis();
+        for key in read_keys(&mut stdin, &mut decoder) {
+            match key {
+                Key::Up => editor.move_cursor(&Direction::Up),
+                Key::Down => editor.move_cursor(&Direction::Down),
+                Key::Left => editor.move_cursor(&Direction::Left),
+                Key::Right => editor.move_cursor(&Direction::Right),
+                Key::Ctrl('c') => break 'stdin,
+                key => editor.input(key),
+            }
+        }
 
-        thread::sleep(Duration::from_millis(max(100 - duration, 0) as u64));
+        thread::sleep(Duration::from_millis(20));
     }
 
     println!("{}{}{}", clear::All, cursor::Show, cursor::Goto(1, 1));
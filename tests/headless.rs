@@ -0,0 +1,180 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env,
+    io::Write,
+    process::{Command, Stdio},
+    rc::Rc,
+};
+use terminal_madness::{
+    dialogue::parse_dialogues,
+    events::Event,
+    flags::parse_triggers,
+    game::Game,
+    helper::parse_maps,
+    input::{Action, Movement},
+    messages::Severity,
+    render::{Buffer, Screen},
+    shared::{Direction, Point},
+    splash::parse_screens,
+};
+
+fn game() -> (Game, Buffer) {
+    let maps = parse_maps(include_str!("../lib/maps")).unwrap();
+    let triggers = parse_triggers(include_str!("../lib/flags"), &maps).unwrap();
+    let mut game = Game::new(
+        maps,
+        parse_dialogues(include_str!("../lib/dialogue")),
+        triggers,
+        parse_screens(include_str!("../lib/splash")),
+    );
+    let buffer = Buffer::new((60, 20));
+    game.subscribe(Box::new(Screen::new(buffer.clone())));
+    game.set_movement(Movement::Step);
+    game.set_seed(1);
+
+    game.step(&Action::Confirm);
+    game.step(&Action::Interact);
+    (game, buffer)
+}
+
+fn walk(game: &mut Game, direction: Direction, steps: usize) -> Vec<Event> {
+    (0..steps)
+        .flat_map(|_| game.step(&Action::Move(direction.to_owned())))
+        .collect()
+}
+
+#[test]
+fn it_should_report_what_came_of_each_step() {
+    let (mut game, _) = game();
+
+    assert_eq!(
+        game.step(&Action::Move(Direction::Up)),
+        vec![
+            Event::PlayerMoved {
+                from: Point { x: 2, y: 7 },
+                to: Point { x: 2, y: 6 },
+            },
+            Event::Changed
+        ]
+    );
+
+    walk(&mut game, Direction::Up, 2);
+    assert_eq!(
+        game.step(&Action::Move(Direction::Left)),
+        vec![
+            Event::Blocked {
+                at: Point { x: 1, y: 4 },
+            },
+            Event::Changed
+        ]
+    );
+
+    assert_eq!(
+        game.step(&Action::Interact),
+        vec![
+            Event::Interacted {
+                at: Point { x: 1, y: 4 },
+                npc: None,
+            },
+            Event::Said {
+                ticks: 6,
+                severity: Severity::Info,
+                text: "you stand next to a wall".to_string(),
+            },
+            Event::Changed
+        ]
+    );
+}
+
+#[test]
+fn it_should_draw_the_map_walked_to() {
+    let (mut game, buffer) = game();
+
+    walk(&mut game, Direction::Up, 6);
+    walk(&mut game, Direction::Right, 13);
+    let events = walk(&mut game, Direction::Up, 1);
+    assert!(events.contains(&Event::Warped {
+        from: (0, 0),
+        to: (0, 1),
+    }));
+
+    game.step(&Action::Interact);
+    let frame = buffer.frame().plain();
+    assert!(frame
+        .iter()
+        .any(|line| line.trim() == "7,4 facing up, empty floor ahead"));
+}
+
+#[test]
+fn it_should_show_the_card_of_an_area_warped_into() {
+    let maps = parse_maps("0|0|3,1|0,0,1\n0,0-2,0\n2,0,3,1,0\n1|0|3,1|1,0,1\n0,0-2,0\n0,0,3,0,0");
+    let screens = parse_screens("0|The Cellar\n1|The Attic\n1|Dust on everything.\n");
+    let mut game = Game::new(maps.unwrap(), HashMap::new(), HashMap::new(), screens);
+    let buffer = Buffer::new((40, 12));
+    game.subscribe(Box::new(Screen::new(buffer.clone())));
+    game.set_movement(Movement::Step);
+    let shows = |text: &str| {
+        buffer
+            .frame()
+            .plain()
+            .iter()
+            .any(|line| line.contains(text))
+    };
+
+    game.step(&Action::Confirm);
+    assert!(shows("║ The Cellar"));
+    game.step(&Action::Interact);
+
+    let events = walk(&mut game, Direction::Right, 2);
+    assert!(events.contains(&Event::Warped {
+        from: (0, 0),
+        to: (1, 0),
+    }));
+    assert!(shows("║ The Attic"));
+    assert!(shows("║ Dust on everything."));
+
+    game.step(&Action::Interact);
+    assert!(!shows("Dust on everything."));
+    assert!(shows("The Attic (1,0)"));
+}
+
+#[test]
+fn it_should_tell_subscribers_what_happened() {
+    let (mut game, _) = game();
+    let blocked = Rc::new(RefCell::new(Vec::new()));
+    let heard = blocked.clone();
+    game.subscribe(Box::new(move |event: &Event| {
+        if let Event::Blocked { at } = event {
+            heard.borrow_mut().push(*at);
+        }
+    }));
+
+    walk(&mut game, Direction::Down, 2);
+    assert_eq!(*blocked.borrow(), vec![Point { x: 2, y: 8 }; 2]);
+}
+
+#[test]
+fn it_should_play_a_script_without_a_terminal() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_terminal-madness"))
+        .args(["--headless", "--seed", "1", "--movement", "step"])
+        .env("HOME", env::temp_dir())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let script = "confirm\ninteract\nup\nwait 10\nleft\n";
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.contains("1,6 facing left, a wall ahead\n"));
+    assert!(stdout.contains("\nticks 14\n"));
+}
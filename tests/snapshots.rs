@@ -0,0 +1,80 @@
+use std::{env, fs, path::PathBuf};
+use terminal_madness::{
+    camera::Camera,
+    helper::parse_maps,
+    map::Map,
+    render::Frame,
+    shared::{Direction, ParsedMap},
+};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+///
+/// The whole of `parsed` as drawn, with the player facing `direction`.
+///
+fn draw(parsed: &ParsedMap, direction: &Direction) -> Vec<String> {
+    let mut map = Map::parse_map(&parsed.grid, &parsed.max, &parsed.player, &parsed.props);
+    map.direction = direction.to_owned();
+
+    let camera = Camera::follow(map.size(), &map.current, map.size());
+    map.grid_lines(&camera, &[])
+}
+
+///
+/// Compares `actual` with the golden file `name`, or writes it there instead
+/// when `UPDATE_SNAPSHOTS` is set; gives back what didn't match.
+///
+fn check(name: &str, actual: &str) -> Option<String> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(name);
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return None;
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(expected) if expected == actual => None,
+        Ok(expected) => Some(format!(
+            "{} differs, expected\n{}\ngot\n{}",
+            name, expected, actual
+        )),
+        Err(error) => Some(format!("{}: {}", name, error)),
+    }
+}
+
+#[test]
+fn it_should_draw_every_map_facing_every_way() {
+    let maps = parse_maps(include_str!("../lib/maps")).unwrap();
+    let mut failed = Vec::new();
+
+    for parsed in &maps {
+        for direction in &DIRECTIONS {
+            let lines = draw(parsed, direction);
+            let name = format!(
+                "map-{}-{}-{}",
+                parsed.area,
+                parsed.part,
+                format!("{:?}", direction).to_lowercase()
+            );
+
+            let plain = Frame::new(&lines).plain().join("\n") + "\n";
+            let ansi = lines.join("\n") + "\n";
+            failed.extend(check(&format!("{}.txt", name), &plain));
+            failed.extend(check(&format!("{}.ansi", name), &ansi));
+        }
+    }
+
+    assert!(
+        failed.is_empty(),
+        "{}\n\nrun with UPDATE_SNAPSHOTS=1 to accept the changes",
+        failed.join("\n\n")
+    );
+}